@@ -0,0 +1,11 @@
+-- migrate:up
+CREATE TYPE cargo_text_source AS ENUM ('ai', 'procedural', 'manual');
+
+ALTER TABLE cargo ADD COLUMN text_source cargo_text_source;
+
+UPDATE cargo SET text_source = 'ai' WHERE name IS NOT NULL;
+
+-- migrate:down
+ALTER TABLE cargo DROP COLUMN text_source;
+
+DROP TYPE IF EXISTS cargo_text_source;
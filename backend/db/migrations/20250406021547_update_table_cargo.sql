@@ -0,0 +1,11 @@
+-- migrate:up
+ALTER TABLE cargo
+	ADD COLUMN suggested_type cargo_type,
+	ADD COLUMN type_confidence REAL,
+	ADD COLUMN flag_reason TEXT;
+
+-- migrate:down
+ALTER TABLE cargo
+	DROP COLUMN suggested_type,
+	DROP COLUMN type_confidence,
+	DROP COLUMN flag_reason;
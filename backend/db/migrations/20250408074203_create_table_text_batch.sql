@@ -0,0 +1,11 @@
+-- migrate:up
+CREATE TABLE IF NOT EXISTS text_batch
+(
+    id         VARCHAR(255) PRIMARY KEY,
+    created_at timestamptz  NOT NULL DEFAULT NOW(),
+    ended_at   timestamptz,
+    cargo_ids  uuid[]       NOT NULL
+);
+
+-- migrate:down
+DROP TABLE IF EXISTS text_batch;
@@ -0,0 +1,5 @@
+-- migrate:up
+ALTER TABLE cargo ADD COLUMN filter_log TEXT;
+
+-- migrate:down
+ALTER TABLE cargo DROP COLUMN filter_log;
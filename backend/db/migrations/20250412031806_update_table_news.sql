@@ -0,0 +1,15 @@
+-- migrate:up
+ALTER TABLE news
+    ADD COLUMN url TEXT,
+    ADD COLUMN publisher TEXT,
+    ADD COLUMN published_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN language TEXT,
+    ADD COLUMN topic TEXT;
+
+-- migrate:down
+ALTER TABLE news
+    DROP COLUMN url,
+    DROP COLUMN publisher,
+    DROP COLUMN published_at,
+    DROP COLUMN language,
+    DROP COLUMN topic;
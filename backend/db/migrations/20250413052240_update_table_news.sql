@@ -0,0 +1,7 @@
+-- migrate:up
+ALTER TABLE news ADD COLUMN canonical_id UUID REFERENCES news (id) ON DELETE SET NULL;
+CREATE INDEX news_canonical_id_idx ON news (canonical_id);
+
+-- migrate:down
+DROP INDEX news_canonical_id_idx;
+ALTER TABLE news DROP COLUMN canonical_id;
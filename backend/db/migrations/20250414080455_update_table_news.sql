@@ -0,0 +1,31 @@
+-- migrate:up
+-- [[:punct:]] only matches ASCII punctuation under the C locale, so full-width CJK punctuation is listed too
+CREATE FUNCTION news_title_key(title TEXT) RETURNS TEXT
+    LANGUAGE sql IMMUTABLE
+    AS $$ SELECT regexp_replace(lower(title), '[[:space:][:punct:]　、。〃〈-】〔-〟！-／：-＠［-｀｛-･‐-‧·]', '', 'g') $$;
+
+-- merge news sharing a key into the oldest one
+CREATE TEMPORARY TABLE news_duplicate AS
+SELECT id, hype, FIRST_VALUE(id) OVER (PARTITION BY news_title_key(title) ORDER BY created_at, id) AS keep_id
+FROM news;
+
+UPDATE news SET hype = merged.hype
+FROM (SELECT keep_id, SUM(hype) AS hype FROM news_duplicate GROUP BY keep_id HAVING COUNT(*) > 1) AS merged
+WHERE news.id = merged.keep_id;
+
+UPDATE news SET canonical_id = duplicate.keep_id
+FROM news_duplicate AS duplicate
+WHERE news.canonical_id = duplicate.id AND duplicate.id <> duplicate.keep_id AND news.id <> duplicate.keep_id;
+
+DELETE FROM news USING news_duplicate AS duplicate
+WHERE news.id = duplicate.id AND duplicate.id <> duplicate.keep_id;
+
+DROP TABLE news_duplicate;
+
+ALTER TABLE news ADD COLUMN title_key TEXT GENERATED ALWAYS AS (news_title_key(title)) STORED;
+ALTER TABLE news ADD CONSTRAINT news_title_key_key UNIQUE (title_key);
+
+-- migrate:down
+ALTER TABLE news DROP CONSTRAINT news_title_key_key;
+ALTER TABLE news DROP COLUMN title_key;
+DROP FUNCTION news_title_key;
@@ -0,0 +1,12 @@
+-- migrate:up
+CREATE TABLE IF NOT EXISTS news_fetch_run
+(
+    id         uuid PRIMARY KEY     DEFAULT gen_random_uuid(),
+    created_at timestamptz NOT NULL DEFAULT NOW(),
+    found      INT         NOT NULL,
+    inserted   INT         NOT NULL,
+    bumped     INT         NOT NULL
+);
+
+-- migrate:down
+DROP TABLE IF EXISTS news_fetch_run;
@@ -0,0 +1,11 @@
+-- migrate:up
+CREATE TYPE news_fetch_outcome AS ENUM ('ok', 'empty', 'partial', 'failed');
+ALTER TABLE news_fetch_run
+    ADD COLUMN outcome news_fetch_outcome NOT NULL DEFAULT 'ok',
+    ADD COLUMN errors TEXT;
+
+-- migrate:down
+ALTER TABLE news_fetch_run
+    DROP COLUMN outcome,
+    DROP COLUMN errors;
+DROP TYPE news_fetch_outcome;
@@ -0,0 +1,11 @@
+-- migrate:up
+CREATE TABLE news_source_run (
+    run_id UUID NOT NULL REFERENCES news_fetch_run (id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    found INTEGER NOT NULL,
+    error TEXT,
+    PRIMARY KEY (run_id, source)
+);
+
+-- migrate:down
+DROP TABLE news_source_run;
@@ -0,0 +1,11 @@
+-- migrate:up
+ALTER TABLE news
+    ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN pinned_until TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN manual BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- migrate:down
+ALTER TABLE news
+    DROP COLUMN hidden,
+    DROP COLUMN pinned_until,
+    DROP COLUMN manual;
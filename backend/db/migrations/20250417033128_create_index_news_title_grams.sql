@@ -0,0 +1,18 @@
+-- migrate:up
+-- trigrams cannot serve the one and two character words common in CJK titles,
+-- single characters and bigrams narrow down the search for words of any length
+CREATE FUNCTION news_grams(value TEXT) RETURNS TEXT[]
+    LANGUAGE sql IMMUTABLE
+    AS $$
+        SELECT ARRAY(
+            SELECT DISTINCT substr(lower(value), position, size)
+            FROM generate_series(1, length(value)) AS position, (VALUES (1), (2)) AS gram (size)
+            WHERE position + size - 1 <= length(value)
+        )
+    $$;
+
+CREATE INDEX news_title_grams_idx ON news USING gin (news_grams(title));
+
+-- migrate:down
+DROP INDEX news_title_grams_idx;
+DROP FUNCTION news_grams;
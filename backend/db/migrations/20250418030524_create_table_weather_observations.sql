@@ -0,0 +1,27 @@
+-- migrate:up
+CREATE TYPE weather_condition AS ENUM ('sunny', 'cloudy', 'rainy');
+CREATE TYPE wind_level AS ENUM ('slow', 'medium', 'fast');
+CREATE TYPE rain_level AS ENUM ('dry', 'light', 'heavy');
+
+CREATE TABLE IF NOT EXISTS weather_observations
+(
+    id          uuid PRIMARY KEY           DEFAULT gen_random_uuid(),
+    created_at  timestamptz       NOT NULL DEFAULT NOW(),
+    station_id  TEXT              NOT NULL,
+    observed_at timestamptz       NOT NULL,
+    condition   weather_condition NOT NULL,
+    description TEXT,
+    temperature DOUBLE PRECISION,
+    wind_speed  DOUBLE PRECISION,
+    wind_level  wind_level        NOT NULL,
+    rainfall    DOUBLE PRECISION,
+    rain_level  rain_level        NOT NULL,
+    humidity    INTEGER,
+    UNIQUE (station_id, observed_at)
+);
+
+-- migrate:down
+DROP TABLE IF EXISTS weather_observations;
+DROP TYPE rain_level;
+DROP TYPE wind_level;
+DROP TYPE weather_condition;
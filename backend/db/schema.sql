@@ -35,6 +35,86 @@ CREATE TYPE public.cargo_type AS ENUM (
 );
 
 
+--
+-- Name: cargo_text_source; Type: TYPE; Schema: public; Owner: -
+--
+
+CREATE TYPE public.cargo_text_source AS ENUM (
+    'ai',
+    'procedural',
+    'manual'
+);
+
+
+--
+-- Name: news_fetch_outcome; Type: TYPE; Schema: public; Owner: -
+--
+
+CREATE TYPE public.news_fetch_outcome AS ENUM (
+    'ok',
+    'empty',
+    'partial',
+    'failed'
+);
+
+
+--
+-- Name: rain_level; Type: TYPE; Schema: public; Owner: -
+--
+
+CREATE TYPE public.rain_level AS ENUM (
+    'dry',
+    'light',
+    'heavy'
+);
+
+
+--
+-- Name: weather_condition; Type: TYPE; Schema: public; Owner: -
+--
+
+CREATE TYPE public.weather_condition AS ENUM (
+    'sunny',
+    'cloudy',
+    'rainy'
+);
+
+
+--
+-- Name: wind_level; Type: TYPE; Schema: public; Owner: -
+--
+
+CREATE TYPE public.wind_level AS ENUM (
+    'slow',
+    'medium',
+    'fast'
+);
+
+
+--
+-- Name: news_grams(text); Type: FUNCTION; Schema: public; Owner: -
+--
+
+CREATE FUNCTION public.news_grams(value text) RETURNS text[]
+    LANGUAGE sql IMMUTABLE
+    AS $$
+        SELECT ARRAY(
+            SELECT DISTINCT substr(lower(value), position, size)
+            FROM generate_series(1, length(value)) AS position, (VALUES (1), (2)) AS gram (size)
+            WHERE position + size - 1 <= length(value)
+        )
+    $$;
+
+
+--
+-- Name: news_title_key(text); Type: FUNCTION; Schema: public; Owner: -
+--
+
+CREATE FUNCTION public.news_title_key(title text) RETURNS text
+    LANGUAGE sql IMMUTABLE
+    AS $$ SELECT regexp_replace(lower(title), '[[:space:][:punct:]　、。〃〈-】〔-〟！-／：-＠［-｀｛-･‐-‧·]', '', 'g') $$;
+
+
 SET default_tablespace = '';
 
 SET default_table_access_method = heap;
@@ -51,7 +131,12 @@ CREATE TABLE public.cargo (
     status public.cargo_status DEFAULT 'shipping'::public.cargo_status NOT NULL,
     name character varying(255),
     description character varying(255),
-    pending boolean DEFAULT false
+    pending boolean DEFAULT false,
+    text_source public.cargo_text_source,
+    suggested_type public.cargo_type,
+    type_confidence real,
+    flag_reason text,
+    filter_log text
 );
 
 
@@ -64,7 +149,44 @@ CREATE TABLE public.news (
     created_at timestamp with time zone DEFAULT now() NOT NULL,
     title text NOT NULL,
     hype integer DEFAULT 0 NOT NULL,
-    updated_at timestamp with time zone DEFAULT now() NOT NULL
+    updated_at timestamp with time zone DEFAULT now() NOT NULL,
+    url text,
+    publisher text,
+    published_at timestamp with time zone,
+    language text,
+    topic text,
+    canonical_id uuid,
+    title_key text GENERATED ALWAYS AS (public.news_title_key(title)) STORED,
+    hidden boolean DEFAULT false NOT NULL,
+    pinned_until timestamp with time zone,
+    manual boolean DEFAULT false NOT NULL
+);
+
+
+--
+-- Name: news_fetch_run; Type: TABLE; Schema: public; Owner: -
+--
+
+CREATE TABLE public.news_fetch_run (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    found integer NOT NULL,
+    inserted integer NOT NULL,
+    bumped integer NOT NULL,
+    outcome public.news_fetch_outcome DEFAULT 'ok'::public.news_fetch_outcome NOT NULL,
+    errors text
+);
+
+
+--
+-- Name: news_source_run; Type: TABLE; Schema: public; Owner: -
+--
+
+CREATE TABLE public.news_source_run (
+    run_id uuid NOT NULL,
+    source text NOT NULL,
+    found integer NOT NULL,
+    error text
 );
 
 
@@ -77,6 +199,46 @@ CREATE TABLE public.schema_migrations (
 );
 
 
+--
+-- Name: text_batch; Type: TABLE; Schema: public; Owner: -
+--
+
+CREATE TABLE public.text_batch (
+    id character varying(255) NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    ended_at timestamp with time zone,
+    cargo_ids uuid[] NOT NULL
+);
+
+
+--
+-- Name: weather_observations; Type: TABLE; Schema: public; Owner: -
+--
+
+CREATE TABLE public.weather_observations (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    station_id text NOT NULL,
+    observed_at timestamp with time zone NOT NULL,
+    condition public.weather_condition NOT NULL,
+    description text,
+    temperature double precision,
+    wind_speed double precision,
+    wind_level public.wind_level NOT NULL,
+    rainfall double precision,
+    rain_level public.rain_level NOT NULL,
+    humidity integer
+);
+
+
+--
+-- Name: news_fetch_run news_fetch_run_pkey; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.news_fetch_run
+    ADD CONSTRAINT news_fetch_run_pkey PRIMARY KEY (id);
+
+
 --
 -- Name: news news_pkey; Type: CONSTRAINT; Schema: public; Owner: -
 --
@@ -85,6 +247,22 @@ ALTER TABLE ONLY public.news
     ADD CONSTRAINT news_pkey PRIMARY KEY (id);
 
 
+--
+-- Name: news news_title_key_key; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.news
+    ADD CONSTRAINT news_title_key_key UNIQUE (title_key);
+
+
+--
+-- Name: news_source_run news_source_run_pkey; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.news_source_run
+    ADD CONSTRAINT news_source_run_pkey PRIMARY KEY (run_id, source);
+
+
 --
 -- Name: cargo payload_pkey; Type: CONSTRAINT; Schema: public; Owner: -
 --
@@ -101,6 +279,60 @@ ALTER TABLE ONLY public.schema_migrations
     ADD CONSTRAINT schema_migrations_pkey PRIMARY KEY (version);
 
 
+--
+-- Name: text_batch text_batch_pkey; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.text_batch
+    ADD CONSTRAINT text_batch_pkey PRIMARY KEY (id);
+
+
+--
+-- Name: weather_observations weather_observations_pkey; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.weather_observations
+    ADD CONSTRAINT weather_observations_pkey PRIMARY KEY (id);
+
+
+--
+-- Name: weather_observations weather_observations_station_id_observed_at_key; Type: CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.weather_observations
+    ADD CONSTRAINT weather_observations_station_id_observed_at_key UNIQUE (station_id, observed_at);
+
+
+--
+-- Name: news_canonical_id_idx; Type: INDEX; Schema: public; Owner: -
+--
+
+CREATE INDEX news_canonical_id_idx ON public.news USING btree (canonical_id);
+
+
+--
+-- Name: news_title_grams_idx; Type: INDEX; Schema: public; Owner: -
+--
+
+CREATE INDEX news_title_grams_idx ON public.news USING gin (public.news_grams(title));
+
+
+--
+-- Name: news news_canonical_id_fkey; Type: FK CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.news
+    ADD CONSTRAINT news_canonical_id_fkey FOREIGN KEY (canonical_id) REFERENCES public.news(id) ON DELETE SET NULL;
+
+
+--
+-- Name: news_source_run news_source_run_run_id_fkey; Type: FK CONSTRAINT; Schema: public; Owner: -
+--
+
+ALTER TABLE ONLY public.news_source_run
+    ADD CONSTRAINT news_source_run_run_id_fkey FOREIGN KEY (run_id) REFERENCES public.news_fetch_run(id) ON DELETE CASCADE;
+
+
 --
 -- PostgreSQL database dump complete
 --
@@ -121,4 +353,17 @@ INSERT INTO public.schema_migrations (version) VALUES
     ('20250316071646'),
     ('20250316072934'),
     ('20250320094612'),
-    ('20250320095456');
+    ('20250320095456'),
+    ('20250405083012'),
+    ('20250406021547'),
+    ('20250408074203'),
+    ('20250410060418'),
+    ('20250412031806'),
+    ('20250413052240'),
+    ('20250414080455'),
+    ('20250414080501'),
+    ('20250415021933'),
+    ('20250415022010'),
+    ('20250416064517'),
+    ('20250417033128'),
+    ('20250418030524');
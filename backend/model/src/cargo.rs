@@ -1,8 +1,9 @@
-use crate::enums::{CargoStatus, CargoType};
+use crate::enums::{CargoStatus, CargoTextSource, CargoType};
 use axum::body::Bytes;
 use axum_typed_multipart::TryFromMultipart;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgQueryResult;
 use sqlx::{query_as, FromRow, PgPool, Postgres};
 use typeshare::typeshare;
 use uuid::Uuid;
@@ -18,6 +19,11 @@ pub struct Cargo {
     pub status: CargoStatus,
     pub name: Option<String>,
     pub description: Option<String>,
+    pub text_source: Option<CargoTextSource>,
+    pub suggested_type: Option<CargoType>,
+    pub type_confidence: Option<f32>,
+    pub flag_reason: Option<String>,
+    pub filter_log: Option<String>,
 }
 
 #[typeshare]
@@ -48,6 +54,26 @@ pub struct CargoTextInfoRequest {
     pub description: String,
 }
 
+/// Which cargoes a batch text generation should cover
+#[typeshare]
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum BackfillScope {
+    /// cargoes without any name or description
+    Undocumented,
+    /// cargoes described by the offline generator
+    Procedural,
+    /// every cargo except manually written ones, e.g. after a prompt change
+    All,
+}
+
+#[typeshare]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextBatchRequest {
+    pub scope: BackfillScope,
+}
+
 impl Cargo {
     pub async fn get_20(pool: &PgPool) -> Vec<Self> {
         sqlx::query_as("SELECT * FROM cargo ORDER BY created_at DESC LIMIT 20")
@@ -56,6 +82,14 @@ impl Cargo {
             .unwrap_or_default()
     }
 
+    /// Cargoes waiting for the next launch, oldest first
+    pub async fn get_delivered(pool: &PgPool) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM cargo WHERE status = 'delivered' ORDER BY created_at")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+    }
+
     pub async fn set_pending_by_id(
         pool: &PgPool,
         id: Uuid,
@@ -78,6 +112,44 @@ impl Cargo {
         .unwrap_or_default()
     }
 
+    pub async fn set_pending_by_ids(
+        pool: &PgPool,
+        ids: &[Uuid],
+        value: bool,
+    ) -> Result<&'static str, sqlx::Error> {
+        sqlx::query("UPDATE cargo SET pending = $1 WHERE id = ANY($2)")
+            .bind(value)
+            .bind(ids)
+            .execute(pool)
+            .await
+            .map(|_| "Ok")
+    }
+
+    pub async fn get_for_backfill(pool: &PgPool, scope: BackfillScope) -> Vec<Self> {
+        let condition = match scope {
+            BackfillScope::Undocumented => "name IS NULL AND description IS NULL",
+            BackfillScope::Procedural => "text_source = 'procedural'",
+            BackfillScope::All => "text_source IS DISTINCT FROM 'manual'",
+        };
+        sqlx::query_as(&format!(
+            "SELECT * FROM cargo WHERE {condition} AND pending = false ORDER BY created_at"
+        ))
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Oldest first, upgraded cargoes leave the set so every one gets its turn
+    pub async fn get_procedural(pool: &PgPool, limit: i64) -> Vec<Self> {
+        sqlx::query_as(
+            "SELECT * FROM cargo WHERE text_source = 'procedural' AND pending = false ORDER BY created_at ASC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+    }
+
     pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Option<Self> {
         sqlx::query_as("SELECT * FROM cargo WHERE id = $1")
             .bind(id)
@@ -117,6 +189,7 @@ impl Cargo {
     pub async fn update_text_info(
         pool: &PgPool,
         info: CargoTextInfoRequest,
+        source: CargoTextSource,
     ) -> Result<String, sqlx::Error> {
         let CargoTextInfoRequest {
             name,
@@ -124,15 +197,74 @@ impl Cargo {
             id,
         } = &info;
 
-        sqlx::query("UPDATE cargo SET name = $1, description = $2 WHERE id = $3")
+        sqlx::query("UPDATE cargo SET name = $1, description = $2, text_source = $3 WHERE id = $4")
             .bind(name)
             .bind(description)
+            .bind(source)
+            .bind(id)
+            .execute(pool)
+            .await
+            .and_then(updated)
+    }
+
+    pub async fn update_type_check(
+        pool: &PgPool,
+        id: Uuid,
+        suggested_type: &CargoType,
+        confidence: f32,
+    ) -> Result<String, sqlx::Error> {
+        sqlx::query("UPDATE cargo SET suggested_type = $1, type_confidence = $2 WHERE id = $3")
+            .bind(suggested_type)
+            .bind(confidence)
             .bind(id)
             .execute(pool)
             .await
             .map(|_| "ok".to_string())
     }
 
+    pub async fn set_type(
+        pool: &PgPool,
+        id: Uuid,
+        cargo_type: &CargoType,
+    ) -> Result<String, sqlx::Error> {
+        sqlx::query("UPDATE cargo SET type = $1 WHERE id = $2")
+            .bind(cargo_type)
+            .bind(id)
+            .execute(pool)
+            .await
+            .and_then(updated)
+    }
+
+    /// Flag a cargo for staff review, `None` clears the flag
+    pub async fn set_flag(
+        pool: &PgPool,
+        id: Uuid,
+        reason: Option<&str>,
+    ) -> Result<String, sqlx::Error> {
+        sqlx::query("UPDATE cargo SET flag_reason = $1 WHERE id = $2")
+            .bind(reason)
+            .bind(id)
+            .execute(pool)
+            .await
+            .and_then(updated)
+    }
+
+    pub async fn set_filter_log(pool: &PgPool, id: Uuid, log: &str) -> Result<String, sqlx::Error> {
+        sqlx::query("UPDATE cargo SET filter_log = $1 WHERE id = $2")
+            .bind(log)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|_| "ok".to_string())
+    }
+
+    pub async fn get_flagged(pool: &PgPool) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM cargo WHERE flag_reason IS NOT NULL ORDER BY created_at DESC")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+    }
+
     pub async fn create(pool: &PgPool, input: CargoInput) -> Self {
         query_as("INSERT INTO cargo (type, paint_time) VALUES ($1, $2) RETURNING *;")
             .bind(input.r#type)
@@ -142,3 +274,11 @@ impl Cargo {
             .unwrap()
     }
 }
+
+/// `RowNotFound` when no cargo has the id
+fn updated(result: PgQueryResult) -> Result<String, sqlx::Error> {
+    match result.rows_affected() {
+        0 => Err(sqlx::Error::RowNotFound),
+        _ => Ok("ok".to_string()),
+    }
+}
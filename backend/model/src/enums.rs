@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
 #[typeshare]
-#[derive(Serialize, Deserialize, Debug, sqlx::Type, TryFromField, Clone)]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, TryFromField, Clone, PartialEq)]
 #[try_from_field(rename_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
 #[sqlx(rename_all = "lowercase", type_name = "cargo_type")]
@@ -25,3 +25,73 @@ pub enum CargoStatus {
     Delivered,
     Launched,
 }
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase", type_name = "cargo_text_source")]
+pub enum CargoTextSource {
+    Ai,
+    Procedural,
+    Manual,
+}
+
+/// How news are ordered, `decay` weighs hype against age
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NewsRank {
+    #[default]
+    Decay,
+    Hype,
+    Recent,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase", type_name = "news_fetch_outcome")]
+pub enum NewsFetchOutcome {
+    Ok,
+    /// no source returned any news, usually the markup changed
+    Empty,
+    /// some sources failed, the news of the others were stored
+    Partial,
+    /// every source returned nothing or failed, or storing the news failed
+    Failed,
+}
+
+/// What the CCTV screen shows, cloudy shares the sunny background
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase", type_name = "weather_condition")]
+pub enum WeatherCondition {
+    Sunny,
+    Cloudy,
+    Rainy,
+}
+
+/// Wind speed bucket, matches the `wind_{slow,medium,fast}` CCTV assets
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase", type_name = "wind_level")]
+pub enum WindLevel {
+    /// up to a gentle breeze, below 3.4 m/s
+    Slow,
+    /// below 8 m/s
+    Medium,
+    Fast,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, sqlx::Type, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase", type_name = "rain_level")]
+pub enum RainLevel {
+    Dry,
+    Light,
+    /// heavy rain or thunderstorms, at least 7.6 mm per hour
+    Heavy,
+}
@@ -1,5 +1,13 @@
 pub mod cargo;
 pub mod enums;
 pub mod news;
+pub mod news_fetch_run;
+pub mod news_source;
+pub mod status;
+#[cfg(test)]
+mod test_db;
+pub mod text_batch;
 pub mod util;
+pub mod weather;
+pub mod weather_provider;
 pub mod ws_msg;
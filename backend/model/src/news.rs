@@ -1,88 +1,644 @@
+use crate::enums::{NewsFetchOutcome, NewsRank};
+use crate::news_fetch_run::NewsFetchRun;
+use crate::news_source::{NewsItem, NewsSource};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
+use std::collections::HashSet;
 use std::error::Error;
-use thirtyfour::prelude::*;
-use utils::runtime::rand_sleep;
+use typeshare::typeshare;
 use uuid::Uuid;
 
-const URL: &str =
-    "https://news.google.com/search?q=%E5%A4%AA%E7%A9%BA&hl=zh-TW&gl=TW&ceid=TW%3Azh-Hant";
+/// Rewrites of a story are linked to it for this many days after it was last seen
+const CLUSTER_WINDOW_DAYS: i32 = 7;
 
-#[derive(Debug, Serialize, FromRow, Deserialize)]
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct News {
-    id: Uuid,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
-    hype: i32,
-    title: String,
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub hype: i32,
+    pub title: String,
+    pub url: Option<String>,
+    pub publisher: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub language: Option<String>,
+    pub topic: Option<String>,
+    /// the story this is a rewrite of, `None` for canonical stories
+    pub canonical_id: Option<Uuid>,
+    /// hidden by the staff, never shown in the ticker
+    pub hidden: bool,
+    /// shown before the ranked news until then
+    pub pinned_until: Option<DateTime<Utc>>,
+    /// written by the staff instead of fetched, ranked right after the pinned news
+    pub manual: bool,
+}
+
+/// A headline written by the staff
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualNewsRequest {
+    pub title: String,
+    pub url: Option<String>,
+    pub publisher: Option<String>,
+    pub pinned_until: Option<DateTime<Utc>>,
+}
+
+/// A word of the trending news and its hype-weighted frequency
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsKeyword {
+    pub word: String,
+    pub weight: f64,
+}
+
+#[typeshare]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsPinRequest {
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewsQuery {
+    pub rank: Option<NewsRank>,
+}
+
+/// Archive search, every word of `q` has to appear in the title
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsSearchQuery {
+    pub q: Option<String>,
+    /// published or first seen at or after
+    pub from: Option<DateTime<Utc>>,
+    /// published or first seen before
+    pub to: Option<DateTime<Utc>>,
+    /// starts at 1
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsSearchResult {
+    pub items: Vec<News>,
+    pub total: i32,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Parameters of the decayed score:
+/// `(hype + 1) * 0.5 ^ (hours since first seen / half life)`,
+/// plus up to `recency_boost` for news seen again within the recency window.
+/// The exponent is capped at 1000 half-lives, beyond that the power underflows
+#[derive(Debug, Clone, Copy)]
+pub struct NewsRankParams {
+    pub half_life_hours: f64,
+    pub recency_boost: f64,
+    pub recency_window_hours: f64,
 }
 
 impl News {
-    pub async fn get_10(pool: &PgPool) -> Vec<String> {
-        let mut news: Vec<Self> =
-            sqlx::query_as("SELECT * from news ORDER BY updated_at DESC LIMIT 30")
-                .fetch_all(pool)
-                .await
-                .unwrap_or_default();
-
-        // sort news by hype
-        news.sort_by(|a, b| b.hype.cmp(&a.hype));
-        news.truncate(10);
-        news.iter().map(|n| n.title.clone()).collect()
-    }
-
-    pub async fn fetch_remote(pool: &PgPool, driver: &WebDriver) -> Result<(), Box<dyn Error>> {
-        tracing::info!("fetching news...");
-        driver.goto(URL).await?;
-        // scroll down
-        rand_sleep(3000).await;
-        driver
-            .execute(
-                "window.scrollTo(0, document.body.scrollHeight);",
-                Vec::new(),
+    /// Visible canonical stories, pinned ones first, then the ones written by the staff
+    pub async fn get_10(pool: &PgPool, rank: NewsRank, params: &NewsRankParams) -> Vec<Self> {
+        let news = match rank {
+            NewsRank::Decay => sqlx::query_as(
+                "SELECT * FROM news WHERE canonical_id IS NULL AND NOT hidden ORDER BY
+                    pinned_until > NOW() IS TRUE DESC,
+                    manual DESC,
+                    (hype + 1) * POWER(0.5, LEAST(EXTRACT(EPOCH FROM NOW() - created_at) / 3600 / $1::FLOAT8, 1000))
+                    + $2::FLOAT8 * GREATEST(0, 1 - EXTRACT(EPOCH FROM NOW() - updated_at) / 3600 / $3::FLOAT8)
+                    DESC
+                LIMIT 10",
+            )
+            .bind(params.half_life_hours)
+            .bind(params.recency_boost)
+            .bind(params.recency_window_hours)
+            .fetch_all(pool)
+            .await,
+            NewsRank::Recent => sqlx::query_as(
+                "SELECT * FROM news WHERE canonical_id IS NULL AND NOT hidden
+                ORDER BY pinned_until > NOW() IS TRUE DESC, manual DESC, COALESCE(published_at, created_at) DESC
+                LIMIT 10",
+            )
+            .fetch_all(pool)
+            .await,
+            // raw hype among the recently updated news
+            NewsRank::Hype => sqlx::query_as(
+                "SELECT * FROM (
+                    SELECT * FROM news WHERE canonical_id IS NULL AND NOT hidden
+                    ORDER BY pinned_until > NOW() IS TRUE DESC, manual DESC, updated_at DESC LIMIT 30
+                ) AS recent
+                ORDER BY pinned_until > NOW() IS TRUE DESC, manual DESC, hype DESC
+                LIMIT 10",
             )
-            .await?;
-        rand_sleep(3000).await;
+            .fetch_all(pool)
+            .await,
+        };
+
+        news.inspect_err(|err| tracing::error!("failed to get news: {err:?}"))
+            .unwrap_or_default()
+    }
+
+    pub async fn get_10_titles(
+        pool: &PgPool,
+        rank: NewsRank,
+        params: &NewsRankParams,
+    ) -> Vec<String> {
+        Self::get_10(pool, rank, params)
+            .await
+            .into_iter()
+            .map(|n| n.title)
+            .collect()
+    }
 
-        // get sections
-        let sections = driver.find_all(By::Css("c-wiz .PO9Zff")).await?;
-        tracing::debug!("got {} sections", sections.len());
+    /// Fetch titles from every source and store them, a source failing does not stop the others.
+    /// New titles are inserted and known ones bumped in a single upsert, so overlapping runs
+    /// cannot store the same title twice. The outcome is recorded as a fetch run.
+    pub async fn fetch_remote(
+        pool: &PgPool,
+        sources: &[Box<dyn NewsSource>],
+        similarity_threshold: f64,
+    ) -> Result<NewsFetchRun, Box<dyn Error + Send + Sync>> {
+        let mut items: Vec<NewsItem> = Vec::new();
+        let mut errors: Vec<String> = Vec::new();
+        let mut source_runs: Vec<(String, i32, Option<String>)> = Vec::new();
+        for source in sources {
+            tracing::info!("fetching news from {}", source.name());
+            match source.fetch().await {
+                Ok(fetched) => {
+                    tracing::debug!("got {} news from {}", fetched.len(), source.name());
+                    source_runs.push((source.name().to_string(), fetched.len() as i32, None));
+                    for item in fetched {
+                        if !items.iter().any(|i| i.title == item.title) {
+                            items.push(item);
+                        }
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("failed to fetch news from {}: {err:?}", source.name());
+                    errors.push(format!("{}: {err}", source.name()));
+                    source_runs.push((source.name().to_string(), 0, Some(err.to_string())));
+                }
+            }
+        }
+
+        let found = items.len() as i32;
+        let failed_sources = errors.len();
+        let (stored, inserted, bumped) = match Self::store(pool, items, similarity_threshold).await
+        {
+            Ok((inserted, bumped)) => (true, inserted, bumped),
+            Err(err) => {
+                tracing::error!("failed to store news: {err:?}");
+                errors.push(format!("store: {err}"));
+                (false, 0, 0)
+            }
+        };
+
+        let outcome = get_fetch_outcome(found, failed_sources, stored);
+        let errors = (!errors.is_empty()).then(|| errors.join("\n"));
+        let run =
+            NewsFetchRun::create(pool, found, inserted, bumped, outcome, errors, &source_runs)
+                .await?;
+
+        tracing::info!(
+            "news fetched ({:?}): {} found, {} new, {} bumped",
+            run.outcome,
+            run.found,
+            run.inserted,
+            run.bumped
+        );
+        Ok(run)
+    }
+
+    /// Insert new titles and bump known ones, returns how many were inserted and bumped
+    async fn store(
+        pool: &PgPool,
+        items: Vec<NewsItem>,
+        similarity_threshold: f64,
+    ) -> Result<(i32, i32), sqlx::Error> {
+        let mut tx = pool.begin().await?;
 
-        let mut titles = Vec::new();
+        // recent canonical stories new titles may be rewrites of
+        let canonicals: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT id, title FROM news WHERE canonical_id IS NULL AND updated_at > NOW() - make_interval(days => $1)",
+        )
+        .bind(CLUSTER_WINDOW_DAYS)
+        .fetch_all(&mut *tx)
+        .await?;
+        let canonical_ids: Vec<Option<Uuid>> = items
+            .iter()
+            .map(|item| find_canonical(&canonicals, &item.title, similarity_threshold))
+            .collect();
+
+        // titles sharing a key within the run are stored once, the first one wins
+        let upserted: Vec<(Uuid, String, Option<Uuid>, bool)> = sqlx::query_as(
+            "INSERT INTO news (title, url, publisher, published_at, language, topic, canonical_id)
+            SELECT DISTINCT ON (news_title_key(title)) title, url, publisher, published_at, language, topic, canonical_id
+            FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TIMESTAMPTZ[], $5::TEXT[], $6::TEXT[], $7::UUID[])
+                WITH ORDINALITY AS item (title, url, publisher, published_at, language, topic, canonical_id, ordinality)
+            ORDER BY news_title_key(title), ordinality
+            ON CONFLICT (title_key) DO UPDATE SET hype = news.hype + 1, updated_at = NOW()
+            RETURNING id, title, canonical_id, xmax = 0 AS inserted",
+        )
+        .bind(items.iter().map(|item| item.title.clone()).collect::<Vec<_>>())
+        .bind(items.iter().map(|item| item.url.clone()).collect::<Vec<_>>())
+        .bind(items.iter().map(|item| item.publisher.clone()).collect::<Vec<_>>())
+        .bind(items.iter().map(|item| item.published_at).collect::<Vec<_>>())
+        .bind(items.iter().map(|item| item.language.clone()).collect::<Vec<_>>())
+        .bind(items.iter().map(|item| item.topic.clone()).collect::<Vec<_>>())
+        .bind(&canonical_ids)
+        .fetch_all(&mut *tx)
+        .await?;
 
-        rand_sleep(3000).await;
-        // get titles in sections
-        for section in sections {
-            let title = section.find(By::Css(".JtKRv")).await?.text().await?;
-            titles.push(title);
+        // new stories of this run may be rewrites of each other
+        let mut new_canonicals: Vec<(Uuid, String)> = Vec::new();
+        let mut links: Vec<(Uuid, Uuid)> = Vec::new();
+        let mut bumps: Vec<Uuid> = Vec::new();
+        for (id, title, canonical_id, inserted) in &upserted {
+            match (canonical_id, inserted) {
+                (Some(canonical_id), _) => bumps.push(*canonical_id),
+                (None, true) => {
+                    match find_canonical(&new_canonicals, title, similarity_threshold) {
+                        Some(canonical_id) => {
+                            links.push((*id, canonical_id));
+                            bumps.push(canonical_id);
+                        }
+                        None => new_canonicals.push((*id, title.clone())),
+                    }
+                }
+                (None, false) => {}
+            }
         }
 
-        rand_sleep(3000).await;
-        // update existed news
-        let existed_titles: Vec<String> = sqlx::query_scalar(
-            "UPDATE news SET hype = hype + 1, updated_at = NOW() WHERE title IN (SELECT UNNEST($1::VARCHAR[])) RETURNING title"
+        let (ids, link_ids): (Vec<Uuid>, Vec<Uuid>) = links.into_iter().unzip();
+        sqlx::query(
+            "UPDATE news SET canonical_id = link.canonical_id
+            FROM UNNEST($1::UUID[], $2::UUID[]) AS link (id, canonical_id)
+            WHERE news.id = link.id",
+        )
+        .bind(&ids)
+        .bind(&link_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        // merge the hype of rewrites into their canonical story
+        sqlx::query(
+            "UPDATE news SET hype = hype + bump.count, updated_at = NOW()
+            FROM (SELECT id, COUNT(*) AS count FROM UNNEST($1::UUID[]) AS id GROUP BY id) AS bump
+            WHERE news.id = bump.id",
         )
-            .bind(&titles)
+        .bind(&bumps)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let inserted = upserted.iter().filter(|row| row.3).count();
+        Ok((inserted as i32, (upserted.len() - inserted) as i32))
+    }
+
+    /// Add a headline written by the staff, a fetched news with the same title becomes manual
+    pub async fn create_manual(
+        pool: &PgPool,
+        input: ManualNewsRequest,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as(
+            "INSERT INTO news (title, url, publisher, pinned_until, manual) VALUES ($1, $2, $3, $4, TRUE)
+            ON CONFLICT (title_key) DO UPDATE SET
+                title = EXCLUDED.title,
+                url = COALESCE(EXCLUDED.url, news.url),
+                publisher = COALESCE(EXCLUDED.publisher, news.publisher),
+                pinned_until = EXCLUDED.pinned_until,
+                manual = TRUE,
+                hidden = FALSE,
+                canonical_id = NULL,
+                updated_at = NOW()
+            RETURNING *",
+        )
+        .bind(input.title.trim())
+        .bind(input.url)
+        .bind(input.publisher)
+        .bind(input.pinned_until)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn set_hidden(pool: &PgPool, id: Uuid, hidden: bool) -> Result<Self, sqlx::Error> {
+        sqlx::query_as("UPDATE news SET hidden = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(hidden)
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Pin until the given time, or unpin with `None`.
+    /// A rewrite resolves to its canonical story, which is the one updated and returned
+    pub async fn set_pinned_until(
+        pool: &PgPool,
+        id: Uuid,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as(
+            "UPDATE news SET pinned_until = $2
+            WHERE id = (SELECT COALESCE(canonical_id, id) FROM news WHERE id = $1)
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(until)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Titles and hype of the visible stories seen within the last `days`
+    pub async fn get_recent_titles(pool: &PgPool, days: i32) -> Vec<(String, i32)> {
+        sqlx::query_as(
+            "SELECT title, hype FROM news
+            WHERE canonical_id IS NULL AND NOT hidden AND updated_at > NOW() - make_interval(days => $1)",
+        )
+        .bind(days)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Search all stored headlines except the hidden ones, latest first
+    pub async fn search(
+        pool: &PgPool,
+        query: NewsSearchQuery,
+    ) -> Result<NewsSearchResult, sqlx::Error> {
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+        let q = query.q.as_deref().unwrap_or_default();
+        let patterns = get_like_patterns(q);
+        let words: Vec<&str> = q.split_whitespace().collect();
+
+        // CJK text has no word boundaries, so words are matched with `ILIKE`,
+        // the index on the single characters and bigrams of the titles narrows them down
+        const CONDITION: &str = "NOT hidden
+            AND news_grams(title) @> (
+                SELECT COALESCE(ARRAY_AGG(gram), '{}') FROM UNNEST($4::TEXT[]) AS word, UNNEST(news_grams(word)) AS gram
+            )
+            AND title ILIKE ALL($1::TEXT[])
+            AND ($2::TIMESTAMPTZ IS NULL OR COALESCE(published_at, created_at) >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(published_at, created_at) < $3)";
+
+        let total: i32 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*)::INT FROM news WHERE {CONDITION}"))
+                .bind(&patterns)
+                .bind(query.from)
+                .bind(query.to)
+                .bind(&words)
+                .fetch_one(pool)
+                .await?;
+        let items = sqlx::query_as(&format!(
+            "SELECT * FROM news WHERE {CONDITION}
+            ORDER BY COALESCE(published_at, created_at) DESC, id
+            LIMIT $5 OFFSET $6"
+        ))
+        .bind(&patterns)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(&words)
+        .bind(i64::from(page_size))
+        .bind(i64::from(page - 1) * i64::from(page_size))
+        .fetch_all(pool)
+        .await?;
+
+        Ok(NewsSearchResult {
+            items,
+            total,
+            page,
+            page_size,
+        })
+    }
+
+    /// Rewrites of a canonical story
+    pub async fn get_variants(pool: &PgPool, id: Uuid) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM news WHERE canonical_id = $1 ORDER BY created_at")
+            .bind(id)
             .fetch_all(pool)
-            .await?;
+            .await
+            .unwrap_or_default()
+    }
+}
 
-        // filter out existed news
-        let new_titles: Vec<String> = titles
-            .into_iter()
-            .filter(|title| !existed_titles.contains(title))
-            .collect();
+/// One `ILIKE` pattern per whitespace separated word, with the wildcards escaped
+fn get_like_patterns(q: &str) -> Vec<String> {
+    q.split_whitespace()
+        .map(|word| {
+            let word = word
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{word}%")
+        })
+        .collect()
+}
+
+/// The most similar story at or above the threshold
+fn find_canonical(canonicals: &[(Uuid, String)], title: &str, threshold: f64) -> Option<Uuid> {
+    canonicals
+        .iter()
+        .map(|(id, canonical)| (id, title_similarity(canonical, title)))
+        .filter(|(_, similarity)| *similarity >= threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| *id)
+}
+
+/// Character bigrams of a title, ignoring whitespace, punctuation and case.
+/// Chinese has no word boundaries, bigrams of characters work well for short headlines.
+fn bigrams(title: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// A run is only failed when nothing could be stored,
+/// news found by the other sources make a source failing a partial run
+fn get_fetch_outcome(found: i32, failed_sources: usize, stored: bool) -> NewsFetchOutcome {
+    if !stored || (failed_sources > 0 && found == 0) {
+        NewsFetchOutcome::Failed
+    } else if failed_sources > 0 {
+        NewsFetchOutcome::Partial
+    } else if found == 0 {
+        NewsFetchOutcome::Empty
+    } else {
+        NewsFetchOutcome::Ok
+    }
+}
+
+/// Dice coefficient of the title bigrams, from 0 (unrelated) to 1 (same)
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+}
 
-        // insert new news
-        for title in new_titles {
-            let result: String =
-                sqlx::query_scalar("INSERT INTO news (title) VALUES ($1) RETURNING title")
-                    .bind(title)
-                    .fetch_one(pool)
-                    .await?;
-            tracing::debug!("news inserted: {result}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::TestDb;
+
+    #[test]
+    fn similar_titles() {
+        let a = "SpaceX星艦第八次試飛 成功回收超重型推進器";
+        let b = "星艦第八次試飛！SpaceX成功回收超重型推進器";
+        let c = "福衛八號首顆衛星 預計年底升空";
+
+        assert_eq!(title_similarity(a, a), 1.0);
+        assert!(title_similarity(a, b) > 0.8);
+        assert!(title_similarity(a, c) < 0.1);
+        assert_eq!(title_similarity(a, "！"), 0.0);
+    }
+
+    #[test]
+    fn fetch_outcome() {
+        assert_eq!(get_fetch_outcome(12, 0, true), NewsFetchOutcome::Ok);
+        assert_eq!(get_fetch_outcome(0, 0, true), NewsFetchOutcome::Empty);
+        // the other sources were stored
+        assert_eq!(get_fetch_outcome(12, 1, true), NewsFetchOutcome::Partial);
+        assert_eq!(get_fetch_outcome(0, 1, true), NewsFetchOutcome::Failed);
+        assert_eq!(get_fetch_outcome(12, 0, false), NewsFetchOutcome::Failed);
+    }
+
+    #[test]
+    fn like_patterns() {
+        assert_eq!(get_like_patterns(" 星艦  試飛 "), vec!["%星艦%", "%試飛%"]);
+        assert_eq!(get_like_patterns("100%_"), vec!["%100\\%\\_%"]);
+        assert!(get_like_patterns("").is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn decay_rank_survives_old_news() {
+        let db = TestDb::create().await;
+        sqlx::query(
+            "INSERT INTO news (title, hype, created_at, updated_at) VALUES
+                ('獵風者衛星 發布首批全球風場資料', 0, NOW(), NOW()),
+                ('福衛八號首顆衛星 預計年底升空', 5, NOW() - INTERVAL '10 years', NOW() - INTERVAL '10 years')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let params = NewsRankParams {
+            half_life_hours: 1.0,
+            recency_boost: 2.0,
+            recency_window_hours: 6.0,
+        };
+
+        let titles = News::get_10_titles(&db.pool, NewsRank::Decay, &params).await;
+        assert_eq!(
+            titles,
+            vec![
+                "獵風者衛星 發布首批全球風場資料",
+                "福衛八號首顆衛星 預計年底升空"
+            ]
+        );
+        db.drop().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn title_key_strips_full_width_punctuation() {
+        let db = TestDb::create().await;
+        let key: String = sqlx::query_scalar("SELECT news_title_key($1)")
+            .bind("「星艦」第八次試飛，成功回收推進器！（SpaceX）")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(key, "星艦第八次試飛成功回收推進器spacex");
+
+        let inserted = sqlx::query(
+            "INSERT INTO news (title) VALUES ('福衛八號、首顆衛星 預計年底升空'), ('福衛八號首顆衛星。預計年底升空')
+            ON CONFLICT (title_key) DO NOTHING",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(inserted.rows_affected(), 1);
+        db.drop().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn pinning_a_rewrite_pins_its_story() {
+        let db = TestDb::create().await;
+        let (story, rewrite): (Uuid, Uuid) = sqlx::query_as(
+            "WITH story AS (INSERT INTO news (title) VALUES ('星艦第八次試飛 成功回收推進器') RETURNING id)
+            INSERT INTO news (title, canonical_id) SELECT 'SpaceX星艦第八次試飛成功', id FROM story
+            RETURNING canonical_id, id",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO news (title, hype, manual) VALUES ('熱門新聞', 9, FALSE), ('館內公告', 0, TRUE)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let until = Utc::now() + chrono::Duration::hours(1);
+        let pinned = News::set_pinned_until(&db.pool, rewrite, Some(until))
+            .await
+            .unwrap();
+        assert_eq!(pinned.id, story);
+
+        let params = NewsRankParams {
+            half_life_hours: 24.0,
+            recency_boost: 2.0,
+            recency_window_hours: 6.0,
+        };
+        for rank in [NewsRank::Decay, NewsRank::Recent, NewsRank::Hype] {
+            let titles = News::get_10_titles(&db.pool, rank, &params).await;
+            assert_eq!(titles[..2], ["星艦第八次試飛 成功回收推進器", "館內公告"]);
         }
 
-        Ok(())
+        let missing = News::set_pinned_until(&db.pool, Uuid::nil(), None).await;
+        assert!(matches!(missing, Err(sqlx::Error::RowNotFound)));
+        db.drop().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn search_short_cjk_words() {
+        let db = TestDb::create().await;
+        sqlx::query(
+            "INSERT INTO news (title) VALUES ('毅力號 在火星 發現有機物'), ('嫦娥六號 月球背面採樣'), ('SpaceX 星艦 試飛')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let search = |q: &str| NewsSearchQuery {
+            q: Some(q.to_string()),
+            from: None,
+            to: None,
+            page: None,
+            page_size: None,
+        };
+        let titles = |result: NewsSearchResult| -> Vec<String> {
+            result.items.into_iter().map(|news| news.title).collect()
+        };
+
+        let result = News::search(&db.pool, search("火星")).await.unwrap();
+        assert_eq!(titles(result), vec!["毅力號 在火星 發現有機物"]);
+        let result = News::search(&db.pool, search("月 採樣")).await.unwrap();
+        assert_eq!(titles(result), vec!["嫦娥六號 月球背面採樣"]);
+        let result = News::search(&db.pool, search("spacex")).await.unwrap();
+        assert_eq!(titles(result), vec!["SpaceX 星艦 試飛"]);
+        // every word has to match
+        let result = News::search(&db.pool, search("火星 月球")).await.unwrap();
+        assert_eq!(result.total, 0);
+        assert_eq!(News::search(&db.pool, search("")).await.unwrap().total, 3);
+        db.drop().await;
     }
 }
@@ -0,0 +1,268 @@
+use crate::enums::NewsFetchOutcome;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use typeshare::typeshare;
+use uuid::Uuid;
+
+/// Statistics of one news fetch
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsFetchRun {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    /// distinct titles returned by the sources
+    pub found: i32,
+    /// titles seen for the first time
+    pub inserted: i32,
+    /// titles already stored, their hype was bumped
+    pub bumped: i32,
+    pub outcome: NewsFetchOutcome,
+    /// one line per failed source
+    pub errors: Option<String>,
+}
+
+/// What one source returned during a fetch
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsSourceRun {
+    pub run_id: Uuid,
+    pub source: String,
+    /// titles returned, before they are merged with the other sources
+    pub found: i32,
+    pub error: Option<String>,
+}
+
+impl NewsSourceRun {
+    fn failed(&self) -> bool {
+        self.error.is_some() || self.found == 0
+    }
+}
+
+/// Health of one news source, unhealthy after too many consecutive empty or failed fetches
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsSourceHealth {
+    pub source: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub warning: Option<String>,
+}
+
+/// Health of the news fetching, unhealthy once any source of the last run is
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsHealth {
+    pub healthy: bool,
+    /// one line per unhealthy source
+    pub warning: Option<String>,
+    pub last_run: Option<NewsFetchRun>,
+    pub sources: Vec<NewsSourceHealth>,
+}
+
+impl NewsFetchRun {
+    /// Record a fetch along with what each source returned
+    pub async fn create(
+        pool: &PgPool,
+        found: i32,
+        inserted: i32,
+        bumped: i32,
+        outcome: NewsFetchOutcome,
+        errors: Option<String>,
+        sources: &[(String, i32, Option<String>)],
+    ) -> Result<Self, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let run: Self = sqlx::query_as(
+            "INSERT INTO news_fetch_run (found, inserted, bumped, outcome, errors) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        )
+        .bind(found)
+        .bind(inserted)
+        .bind(bumped)
+        .bind(outcome)
+        .bind(errors)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO news_source_run (run_id, source, found, error)
+            SELECT $1, * FROM UNNEST($2::TEXT[], $3::INTEGER[], $4::TEXT[])",
+        )
+        .bind(run.id)
+        .bind(sources.iter().map(|s| s.0.clone()).collect::<Vec<_>>())
+        .bind(sources.iter().map(|s| s.1).collect::<Vec<_>>())
+        .bind(sources.iter().map(|s| s.2.clone()).collect::<Vec<_>>())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(run)
+    }
+
+    pub async fn get_20(pool: &PgPool) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM news_fetch_run ORDER BY created_at DESC LIMIT 20")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn get_health(pool: &PgPool, max_failures: u32) -> NewsHealth {
+        let runs = Self::get_20(pool).await;
+        let source_runs = sqlx::query_as(
+            "SELECT source_run.* FROM news_source_run AS source_run
+            JOIN news_fetch_run AS run ON run.id = source_run.run_id
+            WHERE run.id = ANY($1)
+            ORDER BY run.created_at DESC, source_run.source",
+        )
+        .bind(runs.iter().map(|run| run.id).collect::<Vec<_>>())
+        .fetch_all(pool)
+        .await
+        .inspect_err(|err| tracing::error!("failed to get news source runs: {err:?}"))
+        .unwrap_or_default();
+        NewsHealth::from_runs(runs, source_runs, max_failures)
+    }
+}
+
+impl NewsHealth {
+    /// `runs` and `source_runs` are ordered from the latest,
+    /// the sources fetched by the latest run are the ones checked
+    fn from_runs(
+        runs: Vec<NewsFetchRun>,
+        source_runs: Vec<NewsSourceRun>,
+        max_failures: u32,
+    ) -> Self {
+        let last_run = runs.into_iter().next();
+        let sources: Vec<NewsSourceHealth> = source_runs
+            .iter()
+            .filter(|source_run| last_run.as_ref().is_some_and(|run| run.id == source_run.run_id))
+            .map(|latest| {
+                let consecutive_failures = source_runs
+                    .iter()
+                    .filter(|source_run| source_run.source == latest.source)
+                    .take_while(|source_run| source_run.failed())
+                    .count() as u32;
+                let healthy = consecutive_failures < max_failures;
+                let warning = (!healthy).then(|| {
+                    let reason = latest.error.as_deref().unwrap_or("no news found");
+                    format!(
+                        "last {consecutive_failures} news fetches from {} were empty or failed: {reason}",
+                        latest.source
+                    )
+                });
+                NewsSourceHealth {
+                    source: latest.source.clone(),
+                    healthy,
+                    consecutive_failures,
+                    warning,
+                }
+            })
+            .collect();
+
+        let warnings: Vec<&str> = sources
+            .iter()
+            .filter_map(|source| source.warning.as_deref())
+            .collect();
+
+        Self {
+            healthy: warnings.is_empty(),
+            warning: (!warnings.is_empty()).then(|| warnings.join("\n")),
+            last_run,
+            sources,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::TestDb;
+
+    fn run(id: u128) -> NewsFetchRun {
+        NewsFetchRun {
+            id: Uuid::from_u128(id),
+            created_at: Utc::now(),
+            found: 0,
+            inserted: 0,
+            bumped: 0,
+            outcome: NewsFetchOutcome::Ok,
+            errors: None,
+        }
+    }
+
+    fn source_run(run_id: u128, source: &str, found: i32, error: Option<&str>) -> NewsSourceRun {
+        NewsSourceRun {
+            run_id: Uuid::from_u128(run_id),
+            source: source.to_string(),
+            found,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn unhealthy_after_consecutive_failures() {
+        // webdriver keeps failing while rss keeps the run itself going
+        let runs = vec![run(3), run(2), run(1)];
+        let source_runs = vec![
+            source_run(3, "rss:太空", 10, None),
+            source_run(3, "webdriver:太空", 0, Some("timeout")),
+            source_run(2, "rss:太空", 0, None),
+            source_run(2, "webdriver:太空", 0, None),
+            source_run(1, "rss:太空", 8, None),
+            source_run(1, "webdriver:太空", 0, Some("timeout")),
+        ];
+
+        let health = NewsHealth::from_runs(runs, source_runs, 3);
+        assert!(!health.healthy);
+        assert_eq!(health.last_run.unwrap().id, Uuid::from_u128(3));
+        assert!(health.sources[0].healthy);
+        assert_eq!(health.sources[0].consecutive_failures, 0);
+        assert!(!health.sources[1].healthy);
+        assert_eq!(
+            health.warning.as_deref(),
+            Some("last 3 news fetches from webdriver:太空 were empty or failed: timeout")
+        );
+    }
+
+    #[test]
+    fn healthy_below_the_limit() {
+        let source_runs = vec![
+            source_run(2, "rss:太空", 0, Some("503")),
+            source_run(1, "rss:太空", 0, None),
+            source_run(0, "rss:太空", 5, None),
+        ];
+        let health = NewsHealth::from_runs(vec![run(2), run(1), run(0)], source_runs, 3);
+        assert!(health.healthy);
+        assert_eq!(health.sources[0].consecutive_failures, 2);
+
+        // sources no longer configured are not checked
+        let source_runs = vec![source_run(1, "rss:太空", 0, Some("503"))];
+        assert!(NewsHealth::from_runs(vec![run(2), run(1)], source_runs, 1).healthy);
+
+        assert!(NewsHealth::from_runs(vec![], vec![], 3).healthy);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn health_from_recorded_runs() {
+        let db = TestDb::create().await;
+        let sources = [
+            ("rss:太空".to_string(), 4, None),
+            ("webdriver:太空".to_string(), 0, Some("timeout".to_string())),
+        ];
+        for _ in 0..2 {
+            NewsFetchRun::create(&db.pool, 4, 1, 3, NewsFetchOutcome::Partial, None, &sources)
+                .await
+                .unwrap();
+        }
+
+        let health = NewsFetchRun::get_health(&db.pool, 2).await;
+        assert!(!health.healthy);
+        assert_eq!(health.sources.len(), 2);
+        assert!(health.sources[0].healthy);
+        assert_eq!(health.sources[1].consecutive_failures, 2);
+        db.drop().await;
+    }
+}
@@ -0,0 +1,298 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::error::Error;
+use std::sync::LazyLock;
+use thirtyfour::prelude::*;
+use utils::runtime::rand_sleep;
+
+pub type NewsSourceError = Box<dyn Error + Send + Sync>;
+
+const WEBDRIVER_LANGUAGE: &str = "zh-tw";
+
+/// Markup some feeds leave in their titles
+static TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// A headline found by a news source
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NewsItem {
+    pub title: String,
+    pub url: Option<String>,
+    pub publisher: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub language: Option<String>,
+    /// the query the item was found with
+    pub topic: Option<String>,
+}
+
+#[async_trait]
+pub trait NewsSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn fetch(&self) -> Result<Vec<NewsItem>, NewsSourceError>;
+}
+
+fn fill_query(template: &str, query: &str) -> String {
+    template.replace("{query}", &urlencoding::encode(query))
+}
+
+/// Google News appends the publisher to every title, e.g. `標題 - 中央社 CNA`
+fn split_publisher(title: &str) -> (&str, Option<&str>) {
+    match title.rsplit_once(" - ") {
+        Some((headline, publisher))
+            if !headline.trim().is_empty() && publisher.chars().count() <= 20 =>
+        {
+            (headline.trim(), Some(publisher.trim()))
+        }
+        _ => (title.trim(), None),
+    }
+}
+
+/// Parse an RSS or Atom document into news items
+pub fn parse_feed(content: &[u8]) -> Result<Vec<NewsItem>, NewsSourceError> {
+    let feed = feed_rs::parser::parse(content)?;
+
+    let mut items = Vec::new();
+    for entry in feed.entries {
+        let Some(title) = &entry.title else {
+            continue;
+        };
+        let title = TAGS.replace_all(&title.content, "");
+        let (title, title_publisher) = split_publisher(&title);
+        if title.is_empty() {
+            continue;
+        }
+
+        let publisher = title_publisher
+            .map(str::to_string)
+            .or(entry.source)
+            .or_else(|| entry.authors.first().map(|author| author.name.clone()));
+
+        items.push(NewsItem {
+            title: title.to_string(),
+            url: entry.links.first().map(|link| link.href.clone()),
+            publisher,
+            published_at: entry.published.or(entry.updated),
+            language: feed.language.clone(),
+            topic: None,
+        });
+    }
+    Ok(items)
+}
+
+/// An RSS or Atom feed, e.g. the Google News search feed
+pub struct RssSource {
+    name: String,
+    url: String,
+    query: String,
+}
+
+impl RssSource {
+    /// `url_template` may contain `{query}`, which is replaced by the url encoded query
+    pub fn new(url_template: &str, query: &str) -> Self {
+        Self {
+            name: format!("rss:{query}"),
+            url: fill_query(url_template, query),
+            query: query.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl NewsSource for RssSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> Result<Vec<NewsItem>, NewsSourceError> {
+        let content = reqwest::get(&self.url)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let mut items = parse_feed(&content)?;
+        for item in &mut items {
+            item.topic = Some(self.query.clone());
+        }
+        Ok(items)
+    }
+}
+
+/// The page and CSS selectors the WebDriver source scrapes, Google changes its markup without notice
+#[derive(Debug, Clone)]
+pub struct WebDriverTarget {
+    /// `{query}` is replaced by the url encoded query
+    pub url: String,
+    /// one element per news
+    pub section_selector: String,
+    /// the link inside a section, its text is the title
+    pub title_selector: String,
+    pub publisher_selector: String,
+    pub time_selector: String,
+}
+
+/// Scrapes the Google News search page through a headless Chrome
+pub struct WebDriverSource {
+    name: String,
+    url: String,
+    query: String,
+    target: WebDriverTarget,
+    wd_port: u16,
+}
+
+impl WebDriverSource {
+    pub fn new(wd_port: u16, target: &WebDriverTarget, query: &str) -> Self {
+        Self {
+            name: format!("webdriver:{query}"),
+            url: fill_query(&target.url, query),
+            query: query.to_string(),
+            target: target.clone(),
+            wd_port,
+        }
+    }
+
+    async fn scrape(&self, driver: &WebDriver) -> Result<Vec<NewsItem>, NewsSourceError> {
+        let base_url = reqwest::Url::parse(&self.url)?;
+        driver.goto(&self.url).await?;
+        // scroll down
+        rand_sleep(3000).await;
+        driver
+            .execute(
+                "window.scrollTo(0, document.body.scrollHeight);",
+                Vec::new(),
+            )
+            .await?;
+        rand_sleep(3000).await;
+
+        // get sections
+        let sections = driver
+            .find_all(By::Css(&self.target.section_selector))
+            .await?;
+        tracing::debug!("got {} sections", sections.len());
+
+        let mut items = Vec::new();
+
+        rand_sleep(3000).await;
+        // get titles in sections
+        for section in sections {
+            let link = section.find(By::Css(&self.target.title_selector)).await?;
+            let title = link.text().await?;
+            // links are relative, e.g. `./read/CBMi...`
+            let url = link
+                .attr("href")
+                .await?
+                .and_then(|href| base_url.join(&href).ok())
+                .map(String::from);
+            let publisher = match section.find(By::Css(&self.target.publisher_selector)).await {
+                Ok(element) => Some(element.text().await?),
+                Err(_) => None,
+            };
+            let published_at = match section.find(By::Css(&self.target.time_selector)).await {
+                Ok(element) => element
+                    .attr("datetime")
+                    .await?
+                    .and_then(|datetime| datetime.parse().ok()),
+                Err(_) => None,
+            };
+
+            items.push(NewsItem {
+                title,
+                url,
+                publisher,
+                published_at,
+                language: Some(WEBDRIVER_LANGUAGE.to_string()),
+                topic: Some(self.query.clone()),
+            });
+        }
+
+        Ok(items)
+    }
+}
+
+#[async_trait]
+impl NewsSource for WebDriverSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> Result<Vec<NewsItem>, NewsSourceError> {
+        tracing::info!("initializing webdriver");
+        let mut caps = DesiredCapabilities::chrome();
+        caps.add_arg("--headless")?;
+        let driver = WebDriver::new(format!("http://localhost:{}", self.wd_port), caps).await?;
+
+        let result = self.scrape(&driver).await;
+        if driver.quit().await.is_err() {};
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_google_news_rss() {
+        let items = parse_feed(include_bytes!("../fixtures/news/google_news.rss")).unwrap();
+        let titles: Vec<&str> = items.iter().map(|item| item.title.as_str()).collect();
+
+        assert_eq!(
+            titles,
+            vec![
+                "SpaceX 星艦第八次試飛 成功回收超重型推進器",
+                "福衛八號首顆衛星 預計年底升空",
+                "國際太空站迎來新一批補給",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_atom_feed() {
+        let items = parse_feed(include_bytes!("../fixtures/news/space_agency.atom")).unwrap();
+        let titles: Vec<&str> = items.iter().map(|item| item.title.as_str()).collect();
+
+        assert_eq!(
+            titles,
+            vec!["獵風者衛星 發布首批全球風場資料", "太空教育營 開放報名"]
+        );
+    }
+
+    #[test]
+    fn parse_feed_metadata() {
+        let items = parse_feed(include_bytes!("../fixtures/news/google_news.rss")).unwrap();
+        assert_eq!(items[0].publisher.as_deref(), Some("中央社 CNA"));
+        assert_eq!(items[0].language.as_deref(), Some("zh-tw"));
+        assert_eq!(
+            items[0].published_at,
+            Some("2025-03-29T10:00:00Z".parse().unwrap())
+        );
+        assert!(items[0]
+            .url
+            .as_deref()
+            .unwrap()
+            .starts_with("https://news.google.com/rss/articles/"));
+        // no suffix and no source, the feed itself is not the publisher
+        assert_eq!(items[2].publisher, None);
+
+        let items = parse_feed(include_bytes!("../fixtures/news/space_agency.atom")).unwrap();
+        assert_eq!(items[0].publisher.as_deref(), Some("國家太空中心"));
+        assert_eq!(items[1].publisher, None);
+        assert_eq!(
+            items[1].url.as_deref(),
+            Some("https://www.tasa.org.tw/news/2025032801")
+        );
+        // no published time, falls back to updated
+        assert_eq!(
+            items[1].published_at,
+            Some("2025-03-28T02:00:00Z".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn fill_query_encodes_query() {
+        assert_eq!(
+            fill_query("https://example.com/rss?q={query}", "太空 火箭"),
+            "https://example.com/rss?q=%E5%A4%AA%E7%A9%BA%20%E7%81%AB%E7%AE%AD"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use crate::news_fetch_run::NewsHealth;
+use crate::weather::WeatherOverride;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+/// Overview for the staff, whatever needs attention shows up here
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminStatus {
+    pub news: NewsHealth,
+    /// `None` while the live weather is shown
+    pub weather_override: Option<WeatherOverride>,
+}
@@ -0,0 +1,81 @@
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{Executor, PgPool};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// A throwaway database with every migration applied, for tests that need the real queries.
+/// Needs `TEST_DATABASE_URL` to point at a server the tests may create databases on,
+/// the tests using it are ignored by default and run with `cargo test -- --ignored`.
+/// The database uses the C locale, so queries cannot rely on the server's locale to classify text.
+pub struct TestDb {
+    pub pool: PgPool,
+    admin: PgPool,
+    name: String,
+}
+
+impl TestDb {
+    pub async fn create() -> Self {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+        let admin = PgPool::connect(&url).await.unwrap();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let name = format!(
+            "test_{}_{}_{}",
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        admin
+            .execute(
+                format!(
+                    "CREATE DATABASE {name} TEMPLATE template0 ENCODING 'UTF8' LC_COLLATE 'C' LC_CTYPE 'C'"
+                )
+                .as_str(),
+            )
+            .await
+            .unwrap();
+
+        let options = PgConnectOptions::from_str(&url).unwrap().database(&name);
+        let pool = PgPoolOptions::new().connect_with(options).await.unwrap();
+        for migration in migrations() {
+            sqlx::raw_sql(&migration)
+                .execute(&pool)
+                .await
+                .unwrap_or_else(|err| panic!("failed to migrate: {err:?}\n{migration}"));
+        }
+        Self { pool, admin, name }
+    }
+
+    pub async fn drop(self) {
+        self.pool.close().await;
+        self.admin
+            .execute(format!("DROP DATABASE {} WITH (FORCE)", self.name).as_str())
+            .await
+            .unwrap();
+    }
+}
+
+/// The `migrate:up` sections of the dbmate migrations, oldest first
+fn migrations() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../db/migrations");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path).unwrap();
+            let up = content.split("-- migrate:down").next().unwrap_or_default();
+            up.replace("-- migrate:up", "")
+        })
+        .collect()
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use typeshare::typeshare;
+use uuid::Uuid;
+
+/// A message batch submitted to the AI provider, tracked until its results are applied
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TextBatch {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub cargo_ids: Vec<Uuid>,
+}
+
+impl TextBatch {
+    pub async fn create(pool: &PgPool, id: &str, cargo_ids: &[Uuid]) -> Result<Self, sqlx::Error> {
+        sqlx::query_as("INSERT INTO text_batch (id, cargo_ids) VALUES ($1, $2) RETURNING *")
+            .bind(id)
+            .bind(cargo_ids)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn get_open(pool: &PgPool) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM text_batch WHERE ended_at IS NULL ORDER BY created_at")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn get_20(pool: &PgPool) -> Vec<Self> {
+        sqlx::query_as("SELECT * FROM text_batch ORDER BY created_at DESC LIMIT 20")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn set_ended(pool: &PgPool, id: &str) -> Result<String, sqlx::Error> {
+        sqlx::query("UPDATE text_batch SET ended_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|_| "ok".to_string())
+    }
+}
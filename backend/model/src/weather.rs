@@ -0,0 +1,573 @@
+use crate::enums::{RainLevel, WeatherCondition, WindLevel};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::error::Error;
+use typeshare::typeshare;
+
+pub type WeatherError = Box<dyn Error + Send + Sync>;
+
+/// Hourly rainfall from which rain counts as heavy, in mm
+const HEAVY_RAIN_MM: f64 = 7.6;
+
+/// Longest span of the hourly history, a season report covers a month
+const MAX_HISTORY_DAYS: i64 = 31;
+
+/// One hourly observation of a weather station
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherObservation {
+    pub observed_at: DateTime<Utc>,
+    pub condition: WeatherCondition,
+    /// as the station reports it, e.g. 陰有雨
+    pub description: Option<String>,
+    /// °C
+    pub temperature: Option<f64>,
+    /// m/s
+    pub wind_speed: Option<f64>,
+    pub wind_level: WindLevel,
+    /// mm during the last hour
+    pub rainfall: Option<f64>,
+    pub rain_level: RainLevel,
+    /// relative humidity in percent
+    pub humidity: Option<i32>,
+}
+
+impl WeatherObservation {
+    pub fn new(
+        observed_at: DateTime<Utc>,
+        description: Option<String>,
+        temperature: Option<f64>,
+        wind_speed: Option<f64>,
+        rainfall: Option<f64>,
+        humidity: Option<i32>,
+    ) -> Self {
+        let rain_level = get_rain_level(description.as_deref(), rainfall);
+        Self {
+            observed_at,
+            condition: get_condition(description.as_deref(), rain_level),
+            description,
+            temperature,
+            wind_speed,
+            wind_level: get_wind_level(wind_speed),
+            rainfall,
+            rain_level,
+            humidity,
+        }
+    }
+
+    pub fn is_raining(&self) -> bool {
+        self.rain_level != RainLevel::Dry
+    }
+
+    /// Providers report the same observation until the next one, it is only stored once
+    pub async fn create(&self, pool: &PgPool, station_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO weather_observations
+                (station_id, observed_at, condition, description, temperature, wind_speed, wind_level, rainfall, rain_level, humidity)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (station_id, observed_at) DO NOTHING",
+        )
+        .bind(station_id)
+        .bind(self.observed_at)
+        .bind(self.condition)
+        .bind(&self.description)
+        .bind(self.temperature)
+        .bind(self.wind_speed)
+        .bind(self.wind_level)
+        .bind(self.rainfall)
+        .bind(self.rain_level)
+        .bind(self.humidity)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Hourly aggregates of a station between `from` and `to`
+    pub async fn get_hourly(
+        pool: &PgPool,
+        station_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<WeatherHourly>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT date_trunc('hour', observed_at) AS hour,
+                COUNT(*)::INT AS observations,
+                mode() WITHIN GROUP (ORDER BY condition) AS condition,
+                AVG(temperature) AS temperature,
+                MIN(temperature) AS temperature_min,
+                MAX(temperature) AS temperature_max,
+                AVG(wind_speed) AS wind_speed,
+                MAX(wind_level) AS wind_level,
+                MAX(rainfall) AS rainfall,
+                MAX(rain_level) AS rain_level,
+                AVG(humidity)::DOUBLE PRECISION AS humidity
+            FROM weather_observations
+            WHERE station_id = $1 AND observed_at >= $2 AND observed_at < $3
+            GROUP BY hour
+            ORDER BY hour",
+        )
+        .bind(station_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherHistoryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl WeatherHistoryQuery {
+    /// `to` defaults to now and `from` to a day before `to`,
+    /// the span may not be reversed nor longer than [`MAX_HISTORY_DAYS`]
+    pub fn get_range(&self, now: DateTime<Utc>) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+        let to = self.to.unwrap_or(now);
+        let from = self.from.unwrap_or(to - Duration::days(1));
+        if from > to {
+            return Err(format!("from {from} is after to {to}"));
+        }
+        if to - from > Duration::days(MAX_HISTORY_DAYS) {
+            return Err(format!("the history spans at most {MAX_HISTORY_DAYS} days"));
+        }
+        Ok((from, to))
+    }
+}
+
+/// The observations of one hour, the most frequent condition and the strongest wind and rain
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherHourly {
+    pub hour: DateTime<Utc>,
+    pub observations: i32,
+    pub condition: WeatherCondition,
+    /// average °C
+    pub temperature: Option<f64>,
+    pub temperature_min: Option<f64>,
+    pub temperature_max: Option<f64>,
+    /// average m/s
+    pub wind_speed: Option<f64>,
+    pub wind_level: WindLevel,
+    /// the largest hourly rainfall reported, in mm
+    pub rainfall: Option<f64>,
+    pub rain_level: RainLevel,
+    /// average relative humidity in percent
+    pub humidity: Option<f64>,
+}
+
+/// The last successful observation, kept while the providers are failing
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherReport {
+    pub observation: WeatherObservation,
+    pub fetched_at: DateTime<Utc>,
+    /// the latest fetch failed, the observation may be outdated
+    pub stale: bool,
+    /// the observation was forced by the staff
+    pub overridden: bool,
+}
+
+impl WeatherReport {
+    pub fn new(observation: WeatherObservation) -> Self {
+        Self {
+            observation,
+            fetched_at: Utc::now(),
+            stale: false,
+            overridden: false,
+        }
+    }
+}
+
+/// Weather forced by the staff during demos and rehearsals, unset fields keep the live values
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherOverride {
+    pub condition: Option<WeatherCondition>,
+    pub wind_level: Option<WindLevel>,
+    pub rain_level: Option<RainLevel>,
+    pub until: DateTime<Utc>,
+}
+
+impl WeatherOverride {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until > now
+    }
+
+    /// Apply the override to the live observation, rain and condition are kept consistent
+    pub fn apply(&self, live: Option<&WeatherObservation>) -> WeatherObservation {
+        let mut observation = live
+            .cloned()
+            .unwrap_or_else(|| WeatherObservation::new(Utc::now(), None, None, None, None, None));
+
+        let rain_level = match (self.rain_level, self.condition) {
+            (Some(rain_level), _) => rain_level,
+            (None, Some(WeatherCondition::Rainy)) if !observation.is_raining() => RainLevel::Light,
+            (None, Some(WeatherCondition::Rainy)) | (None, None) => observation.rain_level,
+            (None, Some(_)) => RainLevel::Dry,
+        };
+        if rain_level != observation.rain_level {
+            observation.rain_level = rain_level;
+            observation.rainfall = None;
+        }
+        observation.condition = if observation.is_raining() {
+            WeatherCondition::Rainy
+        } else {
+            match self.condition.unwrap_or(observation.condition) {
+                WeatherCondition::Rainy => WeatherCondition::Cloudy,
+                condition => condition,
+            }
+        };
+        if let Some(wind_level) = self.wind_level {
+            observation.wind_level = wind_level;
+            observation.wind_speed = Some(get_typical_wind_speed(wind_level));
+        }
+        observation.description = None;
+        observation
+    }
+}
+
+/// Shown on the CCTV screen when the wind is forced, in m/s
+fn get_typical_wind_speed(wind_level: WindLevel) -> f64 {
+    match wind_level {
+        WindLevel::Slow => 1.8,
+        WindLevel::Medium => 5.4,
+        WindLevel::Fast => 10.2,
+    }
+}
+
+pub fn get_wind_level(wind_speed: Option<f64>) -> WindLevel {
+    match wind_speed {
+        Some(speed) if speed >= 8.0 => WindLevel::Fast,
+        Some(speed) if speed >= 3.4 => WindLevel::Medium,
+        _ => WindLevel::Slow,
+    }
+}
+
+/// The heavier of what the description says and what the rain gauge measured
+pub fn get_rain_level(description: Option<&str>, rainfall: Option<f64>) -> RainLevel {
+    let described = match description {
+        Some(text)
+            if ["雷", "大雨", "豪雨"]
+                .iter()
+                .any(|word| text.contains(word)) =>
+        {
+            RainLevel::Heavy
+        }
+        Some(text) if text.contains('雨') => RainLevel::Light,
+        _ => RainLevel::Dry,
+    };
+    let measured = match rainfall {
+        Some(mm) if mm >= HEAVY_RAIN_MM => RainLevel::Heavy,
+        Some(mm) if mm > 0.0 => RainLevel::Light,
+        _ => RainLevel::Dry,
+    };
+    if measured > described {
+        measured
+    } else {
+        described
+    }
+}
+
+fn get_condition(description: Option<&str>, rain_level: RainLevel) -> WeatherCondition {
+    if rain_level != RainLevel::Dry {
+        return WeatherCondition::Rainy;
+    }
+    match description {
+        Some(text) if text.contains('晴') => WeatherCondition::Sunny,
+        _ => WeatherCondition::Cloudy,
+    }
+}
+
+fn cell_text(row: ElementRef, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+    let text = row
+        .select(&selector)
+        .next()?
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Missing values are shown as `-` or `X`
+fn cell_number(row: ElementRef, selector: &str) -> Option<f64> {
+    cell_text(row, selector)?.parse().ok()
+}
+
+/// The table only shows `04/19 14:00` in Taiwan time, the year is the one that isn't in the future
+fn parse_observed_at(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let taipei = FixedOffset::east_opt(8 * 3600)?;
+    let (date, time) = text.split_once(' ')?;
+    let (month, day) = date.split_once('/')?;
+    let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+    let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+
+    let local_now = now.with_timezone(&taipei);
+    let observed_at = |year| {
+        let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_time(time);
+        taipei
+            .from_local_datetime(&naive)
+            .single()
+            .map(|datetime| datetime.with_timezone(&Utc))
+    };
+    match observed_at(local_now.year()) {
+        Some(datetime) if datetime <= now + Duration::days(1) => Some(datetime),
+        _ => observed_at(local_now.year() - 1),
+    }
+}
+
+/// Parse the 24 hour observation table of a CWA station page, newest row first
+pub fn parse_observation_table(
+    html: &str,
+    now: DateTime<Utc>,
+) -> Result<WeatherObservation, WeatherError> {
+    // the page only holds the rows, which are dropped outside of a table
+    let fragment = Html::parse_fragment(&format!("<table>{html}</table>"));
+    let row_selector = Selector::parse("tr").unwrap();
+    let mut rows = fragment
+        .select(&row_selector)
+        .filter(|row| cell_text(*row, "th[headers=time]").is_some());
+
+    let row = rows.next().ok_or("no observation in the table")?;
+    let time = cell_text(row, "th[headers=time]").unwrap_or_default();
+    let observed_at =
+        parse_observed_at(&time, now).ok_or(format!("invalid observation time: {time}"))?;
+
+    let img_selector = Selector::parse("td[headers=weather] img").unwrap();
+    let description = row
+        .select(&img_selector)
+        .next()
+        .and_then(|img| img.attr("title").or(img.attr("alt")))
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty());
+
+    // the rain column accumulates since midnight
+    let rain = cell_number(row, "td[headers=rain]");
+    let date = time.split_once(' ').map(|(date, _)| date);
+    let previous = rows
+        .next()
+        .filter(|previous| {
+            cell_text(*previous, "th[headers=time]")
+                .is_some_and(|time| time.split_once(' ').map(|(date, _)| date) == date)
+        })
+        .and_then(|previous| cell_number(previous, "td[headers=rain]"));
+    let rainfall = match (rain, previous) {
+        (Some(rain), Some(previous)) => Some((rain - previous).max(0.0)),
+        (rain, _) => rain,
+    };
+
+    Ok(WeatherObservation::new(
+        observed_at,
+        description,
+        cell_number(row, "td[headers=temp] .tem-C"),
+        cell_number(row, "td[headers=w-2] .wind_1"),
+        rainfall,
+        cell_number(row, "td[headers=hum]").map(|humidity| humidity.round() as i32),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::TestDb;
+
+    #[test]
+    fn parse_sunny_observation() {
+        let now = "2025-04-19T06:10:00Z".parse().unwrap();
+        let observation =
+            parse_observation_table(include_str!("../fixtures/weather/C0C63_sunny.html"), now)
+                .unwrap();
+
+        assert_eq!(
+            observation,
+            WeatherObservation {
+                observed_at: "2025-04-19T06:00:00Z".parse().unwrap(),
+                condition: WeatherCondition::Sunny,
+                description: Some("晴".to_string()),
+                temperature: Some(27.4),
+                wind_speed: Some(2.6),
+                wind_level: WindLevel::Slow,
+                rainfall: Some(0.0),
+                rain_level: RainLevel::Dry,
+                humidity: Some(62),
+            }
+        );
+        assert!(!observation.is_raining());
+    }
+
+    #[test]
+    fn parse_rainy_observation() {
+        // just after new year, the previous row is from last year
+        let now = "2025-12-31T16:05:00Z".parse().unwrap();
+        let observation =
+            parse_observation_table(include_str!("../fixtures/weather/C0C63_rainy.html"), now)
+                .unwrap();
+
+        assert_eq!(
+            observation.observed_at,
+            "2025-12-31T16:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(observation.condition, WeatherCondition::Rainy);
+        assert_eq!(observation.rain_level, RainLevel::Heavy);
+        assert_eq!(observation.wind_level, WindLevel::Fast);
+        // the accumulation restarted at midnight
+        assert_eq!(observation.rainfall, Some(9.5));
+        assert_eq!(observation.humidity, Some(97));
+        assert!(observation.is_raining());
+    }
+
+    #[test]
+    fn parse_missing_values() {
+        let now = "2025-04-19T07:10:00Z".parse().unwrap();
+        let observation =
+            parse_observation_table(include_str!("../fixtures/weather/C0C63_missing.html"), now)
+                .unwrap();
+
+        assert_eq!(observation.description, None);
+        assert_eq!(observation.temperature, None);
+        assert_eq!(observation.wind_level, WindLevel::Slow);
+        assert_eq!(observation.rainfall, None);
+        assert_eq!(observation.condition, WeatherCondition::Cloudy);
+
+        assert!(parse_observation_table("<p>維護中</p>", now).is_err());
+    }
+
+    #[test]
+    fn override_keeps_rain_and_condition_consistent() {
+        let now = "2025-04-19T06:10:00Z".parse().unwrap();
+        let live =
+            parse_observation_table(include_str!("../fixtures/weather/C0C63_sunny.html"), now)
+                .unwrap();
+        let mut weather_override = WeatherOverride {
+            condition: Some(WeatherCondition::Rainy),
+            wind_level: Some(WindLevel::Fast),
+            rain_level: None,
+            until: now + Duration::minutes(30),
+        };
+        assert!(weather_override.is_active(now));
+        assert!(!weather_override.is_active(now + Duration::hours(1)));
+
+        let observation = weather_override.apply(Some(&live));
+        assert_eq!(observation.condition, WeatherCondition::Rainy);
+        assert_eq!(observation.rain_level, RainLevel::Light);
+        assert_eq!(observation.wind_level, WindLevel::Fast);
+        assert_eq!(observation.temperature, live.temperature);
+
+        // forcing the rain alone turns the sky rainy
+        weather_override.condition = None;
+        weather_override.rain_level = Some(RainLevel::Heavy);
+        assert_eq!(
+            weather_override.apply(None).condition,
+            WeatherCondition::Rainy
+        );
+
+        // a dry rainy sky makes no sense
+        weather_override.condition = Some(WeatherCondition::Rainy);
+        weather_override.rain_level = Some(RainLevel::Dry);
+        assert_eq!(
+            weather_override.apply(Some(&live)).condition,
+            WeatherCondition::Cloudy
+        );
+    }
+
+    #[test]
+    fn rain_level_from_description_and_gauge() {
+        assert_eq!(get_rain_level(Some("多雲時陰"), Some(0.0)), RainLevel::Dry);
+        assert_eq!(get_rain_level(Some("陰有雨"), Some(0.0)), RainLevel::Light);
+        assert_eq!(get_rain_level(Some("多雲"), Some(12.0)), RainLevel::Heavy);
+        assert_eq!(get_rain_level(Some("雷雨"), None), RainLevel::Heavy);
+    }
+
+    #[test]
+    fn history_range() {
+        let now: DateTime<Utc> = "2025-04-19T06:00:00Z".parse().unwrap();
+        let query = |from: Option<&str>, to: Option<&str>| WeatherHistoryQuery {
+            from: from.map(|from| from.parse().unwrap()),
+            to: to.map(|to| to.parse().unwrap()),
+        };
+
+        assert_eq!(
+            query(None, None).get_range(now),
+            Ok((now - Duration::days(1), now))
+        );
+        assert!(
+            query(Some("2025-04-19T00:00:00Z"), Some("2025-04-18T00:00:00Z"))
+                .get_range(now)
+                .is_err()
+        );
+        assert!(
+            query(Some("2025-03-01T00:00:00Z"), Some("2025-04-01T00:00:00Z"))
+                .get_range(now)
+                .is_ok()
+        );
+        assert!(query(Some("2025-01-01T00:00:00Z"), None)
+            .get_range(now)
+            .is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn hourly_aggregates() {
+        let db = TestDb::create().await;
+        let observation =
+            |time: &str, description: &str, temperature, wind_speed, rainfall, humidity| {
+                WeatherObservation::new(
+                    format!("2025-04-19T{time}:00Z").parse().unwrap(),
+                    Some(description.to_string()),
+                    Some(temperature),
+                    Some(wind_speed),
+                    Some(rainfall),
+                    Some(humidity),
+                )
+            };
+        let observations = [
+            observation("10:00", "晴", 20.0, 2.0, 0.0, 60),
+            observation("10:20", "晴", 22.0, 9.0, 0.0, 70),
+            observation("10:40", "陰有雨", 18.0, 4.0, 1.0, 80),
+            observation("11:00", "多雲", 17.0, 1.0, 0.0, 90),
+            // outside the range
+            observation("12:00", "晴", 30.0, 1.0, 0.0, 50),
+        ];
+        for observation in &observations {
+            observation.create(&db.pool, "C0C63").await.unwrap();
+        }
+        observations[0].create(&db.pool, "C0C63").await.unwrap();
+        observations[0].create(&db.pool, "466920").await.unwrap();
+
+        let hourly = WeatherObservation::get_hourly(
+            &db.pool,
+            "C0C63",
+            "2025-04-19T10:00:00Z".parse().unwrap(),
+            "2025-04-19T12:00:00Z".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(hourly.len(), 2);
+
+        let first = &hourly[0];
+        assert_eq!(first.hour, observations[0].observed_at);
+        assert_eq!(first.observations, 3);
+        assert_eq!(first.condition, observations[0].condition);
+        assert_eq!(first.temperature, Some(20.0));
+        assert_eq!(first.temperature_min, Some(18.0));
+        assert_eq!(first.temperature_max, Some(22.0));
+        assert_eq!(first.wind_speed, Some(5.0));
+        assert_eq!(first.wind_level, WindLevel::Fast);
+        assert_eq!(first.rainfall, Some(1.0));
+        assert_eq!(first.rain_level, RainLevel::Light);
+        assert_eq!(first.humidity, Some(70.0));
+        assert_eq!(hourly[1].observations, 1);
+        db.drop().await;
+    }
+}
@@ -0,0 +1,240 @@
+use crate::weather::{parse_observation_table, WeatherError, WeatherObservation};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Missing values of the open data API are reported as -99, some stations use -999 and friends
+const MISSING_VALUE: f64 = -90.0;
+
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn fetch(&self) -> Result<WeatherObservation, WeatherError>;
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    records: ApiRecords<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiRecords<T> {
+    station: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ObsTime {
+    date_time: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WeatherElement {
+    weather: Option<String>,
+    air_temperature: Option<f64>,
+    wind_speed: Option<f64>,
+    relative_humidity: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WeatherStation {
+    obs_time: ObsTime,
+    weather_element: WeatherElement,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Precipitation {
+    precipitation: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct RainfallElement {
+    #[serde(rename = "Past1hr")]
+    past_1hr: Precipitation,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RainStation {
+    rainfall_element: RainfallElement,
+}
+
+fn valid(value: Option<f64>) -> Option<f64> {
+    value.filter(|value| *value > MISSING_VALUE)
+}
+
+fn first_station<T: DeserializeOwned>(content: &[u8]) -> Result<T, WeatherError> {
+    let response: ApiResponse<T> = serde_json::from_slice(content)?;
+    response
+        .records
+        .station
+        .into_iter()
+        .next()
+        .ok_or_else(|| "station not found in the response".into())
+}
+
+/// Parse an `O-A0001-001` response, `rain` is the `O-A0002-001` response of the same station
+///
+/// The weather dataset only has the rain since midnight, the hourly rain comes from the rain gauges.
+pub fn parse_api_observation(
+    weather: &[u8],
+    rain: Option<&[u8]>,
+) -> Result<WeatherObservation, WeatherError> {
+    let station: WeatherStation = first_station(weather)?;
+    let element = station.weather_element;
+    let rainfall = rain
+        .and_then(|rain| first_station::<RainStation>(rain).ok())
+        .and_then(|station| valid(station.rainfall_element.past_1hr.precipitation));
+
+    Ok(WeatherObservation::new(
+        station.obs_time.date_time,
+        element
+            .weather
+            .map(|weather| weather.trim().to_string())
+            .filter(|weather| !weather.is_empty() && !weather.starts_with("-99")),
+        valid(element.air_temperature),
+        valid(element.wind_speed),
+        rainfall,
+        valid(element.relative_humidity).map(|humidity| humidity.round() as i32),
+    ))
+}
+
+/// The Central Weather Administration open data API, needs an API key
+pub struct CwaApiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    station_id: String,
+}
+
+impl CwaApiProvider {
+    /// `base_url` is the datastore, e.g. `https://opendata.cwa.gov.tw/api/v1/rest/datastore`
+    pub fn new(base_url: &str, api_key: &str, station_id: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            station_id: station_id.to_string(),
+        }
+    }
+
+    async fn get_dataset(&self, dataset: &str) -> Result<Vec<u8>, WeatherError> {
+        let content = self
+            .client
+            .get(format!("{}/{dataset}", self.base_url))
+            .query(&[
+                ("Authorization", self.api_key.as_str()),
+                ("StationId", self.station_id.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(content.to_vec())
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for CwaApiProvider {
+    fn name(&self) -> &str {
+        "cwa"
+    }
+
+    async fn fetch(&self) -> Result<WeatherObservation, WeatherError> {
+        let weather = self.get_dataset("O-A0001-001").await?;
+        // not every weather station has a rain gauge
+        let rain = match self.get_dataset("O-A0002-001").await {
+            Ok(rain) => Some(rain),
+            Err(error) => {
+                tracing::warn!(
+                    "failed to get the hourly rain of {}: {error}",
+                    self.station_id
+                );
+                None
+            }
+        };
+        parse_api_observation(&weather, rain.as_deref())
+    }
+}
+
+/// Scrapes the 24 hour observation table of the station page
+pub struct CwaPageProvider {
+    url: String,
+}
+
+impl CwaPageProvider {
+    /// `url_template` may contain `{station}`, which is replaced by the station id
+    pub fn new(url_template: &str, station_id: &str) -> Self {
+        Self {
+            url: url_template.replace("{station}", station_id),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for CwaPageProvider {
+    fn name(&self) -> &str {
+        "page"
+    }
+
+    async fn fetch(&self) -> Result<WeatherObservation, WeatherError> {
+        let html = reqwest::get(&self.url)
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_observation_table(&html, Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{RainLevel, WeatherCondition, WindLevel};
+
+    #[test]
+    fn parse_open_data_observation() {
+        let observation = parse_api_observation(
+            include_bytes!("../fixtures/weather/O-A0001-001.json"),
+            Some(include_bytes!("../fixtures/weather/O-A0002-001.json")),
+        )
+        .unwrap();
+
+        assert_eq!(
+            observation,
+            WeatherObservation {
+                observed_at: "2025-04-19T06:00:00Z".parse().unwrap(),
+                condition: WeatherCondition::Rainy,
+                description: Some("多雲".to_string()),
+                temperature: Some(26.8),
+                wind_speed: Some(4.1),
+                wind_level: WindLevel::Medium,
+                rainfall: Some(2.0),
+                rain_level: RainLevel::Light,
+                humidity: Some(71),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_open_data_missing_values() {
+        let weather = br#"{"records":{"Station":[{"ObsTime":{"DateTime":"2025-04-19T14:00:00+08:00"},"WeatherElement":{"Weather":"-99","AirTemperature":-99.0,"WindSpeed":-99.0,"RelativeHumidity":-99}}]}}"#;
+        let observation = parse_api_observation(weather, Some(b"{}")).unwrap();
+
+        assert_eq!(observation.description, None);
+        assert_eq!(observation.temperature, None);
+        assert_eq!(observation.wind_speed, None);
+        assert_eq!(observation.humidity, None);
+        assert_eq!(observation.rainfall, None);
+
+        let unknown_station = br#"{"records":{"Station":[]}}"#;
+        assert!(parse_api_observation(unknown_station, None).is_err());
+    }
+}
@@ -1,53 +1,340 @@
 use crate::enums::CargoType;
-use serde_json::json;
+use crate::news::News;
+use crate::weather::WeatherReport;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use typeshare::typeshare;
 
-pub struct WSMsg;
+/// Bumped whenever a message changes shape
+pub const WS_PROTOCOL_VERSION: u32 = 3;
 
-impl WSMsg {
-    pub fn cargo(cargo_type: CargoType, id: &str, directory: &str) -> String {
-        let value = json!({
-            "data": {
-                "type": "cargo",
-                "cargo_type": cargo_type,
-                "id": id,
-                "directory": directory,
-            }
-        });
+/// A cargo was sent, its texture is ready at `directory`
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CargoEvent {
+    pub cargo_type: CargoType,
+    pub id: String,
+    pub directory: String,
+}
 
-        serde_json::to_string(&value).unwrap()
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchEvent {
+    pub cargo_amount: u32,
+}
+
+/// Visitors counted by the camera at the entrance
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PopulationEvent {
+    pub amount: u32,
+}
+
+/// Everything a display shows, sent first when a socket connects.
+/// The fields of topics the client did not subscribe to are left empty
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEvent {
+    /// `None` until the first observation, unless overridden
+    pub weather: Option<WeatherReport>,
+    /// `None` until the visitor counter reports
+    pub population: Option<PopulationEvent>,
+    pub seconds_to_launch: Option<u32>,
+    /// delivered cargoes waiting for the next launch, oldest first
+    pub cargoes: Vec<CargoEvent>,
+    pub news: Vec<News>,
+}
+
+/// What a client can subscribe to, one per `ServerMessage` type
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WSTopic {
+    Cargo,
+    Launch,
+    Weather,
+    Population,
+    News,
+}
+
+impl WSTopic {
+    /// Comma separated topic names, e.g. `weather,population`
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                serde_json::from_value(serde_json::Value::String(name.to_string()))
+                    .map_err(|_| format!("unknown topic: {name}"))
+            })
+            .collect()
     }
+}
 
-    pub fn launch(cargo_amount: usize) -> String {
-        let value = json!({
-            "data": {
-                "type": "launch",
-                "cargo_amount": cargo_amount,
-            }
-        });
+/// Broadcast from the server to every display
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Cargo(CargoEvent),
+    Launch(LaunchEvent),
+    Weather(WeatherReport),
+    Population(PopulationEvent),
+    /// the current top news of the ticker
+    News(Vec<News>),
+    /// only sent to a client that just connected
+    Snapshot(SnapshotEvent),
+    /// sent instead of the events a client missed when they are no longer kept,
+    /// replaces everything the client shows
+    Resync(SnapshotEvent),
+}
 
-        serde_json::to_string(&value).unwrap()
+impl ServerMessage {
+    /// `None` for messages every client receives
+    pub fn topic(&self) -> Option<WSTopic> {
+        match self {
+            Self::Cargo(_) => Some(WSTopic::Cargo),
+            Self::Launch(_) => Some(WSTopic::Launch),
+            Self::Weather(_) => Some(WSTopic::Weather),
+            Self::Population(_) => Some(WSTopic::Population),
+            Self::News(_) => Some(WSTopic::News),
+            Self::Snapshot(_) | Self::Resync(_) => None,
+        }
     }
+}
 
-    pub fn weather(is_raining: bool) -> String {
-        let value = json!({
-            "data": {
-                "type": "weather",
-                "raining": is_raining,
-            }
+/// Sent by clients, e.g. the visitor counter
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// rebroadcast to every display
+    Population(PopulationEvent),
+    /// receive these topics as well, a client still receiving every topic
+    /// switches to only these
+    Subscribe(Vec<WSTopic>),
+    /// stop receiving these topics
+    Unsubscribe(Vec<WSTopic>),
+}
+
+/// The topics a client receives, every topic until it subscribes to some
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WSSubscription {
+    /// `None` for every topic
+    topics: Option<HashSet<WSTopic>>,
+}
+
+impl WSSubscription {
+    pub fn new(topics: Option<Vec<WSTopic>>) -> Self {
+        Self {
+            topics: topics.map(|topics| topics.into_iter().collect()),
+        }
+    }
+
+    pub fn subscribe(&mut self, topics: &[WSTopic]) {
+        if topics.is_empty() {
+            return;
+        }
+        self.topics
+            .get_or_insert_with(HashSet::new)
+            .extend(topics.iter().copied());
+    }
+
+    pub fn unsubscribe(&mut self, topics: &[WSTopic]) {
+        let subscribed = self.topics.get_or_insert_with(|| {
+            [
+                WSTopic::Cargo,
+                WSTopic::Launch,
+                WSTopic::Weather,
+                WSTopic::Population,
+                WSTopic::News,
+            ]
+            .into_iter()
+            .collect()
         });
+        for topic in topics {
+            subscribed.remove(topic);
+        }
+    }
+
+    pub fn accepts(&self, topic: WSTopic) -> bool {
+        self.topics
+            .as_ref()
+            .is_none_or(|topics| topics.contains(&topic))
+    }
+
+    pub fn accepts_message(&self, message: &ServerMessage) -> bool {
+        message.topic().is_none_or(|topic| self.accepts(topic))
+    }
+}
+
+/// What goes over the socket from the server
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WSMsg {
+    pub version: u32,
+    /// picked at random when the server starts, events are numbered anew after a restart
+    pub boot: u32,
+    /// the number of the event, a snapshot has the number of the last event it includes
+    pub seq: u32,
+    pub data: ServerMessage,
+}
+
+impl WSMsg {
+    pub fn new(position: WSPosition, data: ServerMessage) -> Self {
+        Self {
+            version: WS_PROTOCOL_VERSION,
+            boot: position.boot,
+            seq: position.seq,
+            data,
+        }
+    }
+
+    pub fn position(&self) -> WSPosition {
+        WSPosition {
+            boot: self.boot,
+            seq: self.seq,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// An event of a server run, where a reconnecting client left off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WSPosition {
+    pub boot: u32,
+    pub seq: u32,
+}
+
+impl WSPosition {
+    /// `boot:seq`, as in the id of a Server-Sent Event
+    pub fn parse(value: &str) -> Option<Self> {
+        let (boot, seq) = value.trim().split_once(':')?;
+        Some(Self {
+            boot: boot.parse().ok()?,
+            seq: seq.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for WSPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.boot, self.seq)
+    }
+}
 
-        serde_json::to_string(&value).unwrap()
+/// The latest broadcast events, numbered from 1 in the order they were sent
+#[derive(Debug, Clone)]
+pub struct WSReplayBuffer {
+    capacity: usize,
+    boot: u32,
+    /// the number of the latest event, 0 before the first one
+    seq: u32,
+    events: VecDeque<WSMsg>,
+}
+
+impl WSReplayBuffer {
+    pub fn new(capacity: usize, boot: u32) -> Self {
+        Self {
+            capacity,
+            boot,
+            seq: 0,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The latest event
+    pub fn position(&self) -> WSPosition {
+        WSPosition {
+            boot: self.boot,
+            seq: self.seq,
+        }
     }
 
-    pub fn population(amount: u32) -> String {
-        let value = json!({
-            "data": {
-                "type": "population",
-                "amount": amount,
+    /// Number the event and keep it, the oldest event is dropped once full
+    pub fn push(&mut self, data: ServerMessage) -> WSMsg {
+        self.seq += 1;
+        let msg = WSMsg::new(self.position(), data);
+        if self.capacity > 0 {
+            if self.events.len() == self.capacity {
+                self.events.pop_front();
             }
-        });
+            self.events.push_back(msg.clone());
+        }
+        msg
+    }
 
-        serde_json::to_string(&value).unwrap()
+    /// The events after `position`, `None` when some of them were dropped already
+    /// or `position` is from before a restart
+    pub fn since(&self, position: WSPosition) -> Option<Vec<WSMsg>> {
+        let WSPosition { boot, seq } = position;
+        let oldest = self.events.front().map_or(self.seq + 1, |event| event.seq);
+        if boot != self.boot || seq > self.seq || seq + 1 < oldest {
+            return None;
+        }
+        Some(
+            self.events
+                .iter()
+                .filter(|event| event.seq > seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// What goes over the socket from a client
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WSClientMsg {
+    pub version: u32,
+    pub data: ClientMessage,
+}
+
+/// The visitor counter from before the protocol was versioned,
+/// `{"data":{"type":"population","amount":4}}`
+#[derive(Deserialize)]
+struct LegacyClientMsg {
+    data: LegacyClientMessage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LegacyClientMessage {
+    Population { amount: u32 },
+}
+
+impl WSClientMsg {
+    /// Versions after [`WS_PROTOCOL_VERSION`] are rejected,
+    /// the unversioned legacy shape is read as version 0
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let msg = match serde_json::from_str::<Self>(text) {
+            Ok(msg) => msg,
+            Err(error) => {
+                let Ok(LegacyClientMsg { data }) = serde_json::from_str(text) else {
+                    return Err(error);
+                };
+                let LegacyClientMessage::Population { amount } = data;
+                return Ok(Self {
+                    version: 0,
+                    data: ClientMessage::Population(PopulationEvent { amount }),
+                });
+            }
+        };
+        if msg.version == 0 || msg.version > WS_PROTOCOL_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported protocol version {}",
+                msg.version
+            )));
+        }
+        Ok(msg)
     }
 }
 
@@ -58,16 +345,152 @@ mod tests {
 
     #[test]
     fn cargo_msg() {
-        let result = WSMsg::cargo(
-            CargoType::Water,
-            "de426c3d-67b4-47c4-8648-8339b0f69c8f",
-            "test",
+        let msg = WSMsg::new(
+            WSPosition { boot: 3, seq: 7 },
+            ServerMessage::Cargo(CargoEvent {
+                cargo_type: CargoType::Water,
+                id: "de426c3d-67b4-47c4-8648-8339b0f69c8f".to_string(),
+                directory: "test".to_string(),
+            }),
         );
+        let json = msg.to_json();
         assert_eq!(
-            serde_json::from_str::<Value>(&result).unwrap(),
+            serde_json::from_str::<Value>(&json).unwrap(),
             serde_json::from_str::<Value>(
-                r#"{"type":"cargo","cargo_type":"water","id":"de426c3d-67b4-47c4-8648-8339b0f69c8f","directory":"test"}"#
+                r#"{"version":3,"boot":3,"seq":7,"data":{"type":"cargo","content":{"cargoType":"water","id":"de426c3d-67b4-47c4-8648-8339b0f69c8f","directory":"test"}}}"#
             ).unwrap()
         );
+        assert_eq!(serde_json::from_str::<WSMsg>(&json).unwrap(), msg);
+    }
+
+    #[test]
+    fn server_msg_round_trip() {
+        for data in [
+            ServerMessage::Launch(LaunchEvent { cargo_amount: 3 }),
+            ServerMessage::Population(PopulationEvent { amount: 12 }),
+            ServerMessage::News(Vec::new()),
+            ServerMessage::Snapshot(SnapshotEvent {
+                weather: None,
+                population: Some(PopulationEvent { amount: 5 }),
+                seconds_to_launch: Some(42),
+                cargoes: Vec::new(),
+                news: Vec::new(),
+            }),
+        ] {
+            let msg = WSMsg::new(WSPosition { boot: 3, seq: 1 }, data);
+            assert_eq!(serde_json::from_str::<WSMsg>(&msg.to_json()).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn client_msg() {
+        let msg = WSClientMsg::parse(
+            r#"{"version":1,"data":{"type":"population","content":{"amount":4}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.data,
+            ClientMessage::Population(PopulationEvent { amount: 4 })
+        );
+        assert_eq!(
+            WSClientMsg::parse(&serde_json::to_string(&msg).unwrap()).unwrap(),
+            msg
+        );
+
+        // the visitor counter still sends the legacy shape
+        assert_eq!(
+            WSClientMsg::parse(r#"{"data":{"type":"population","amount":4}}"#).unwrap(),
+            WSClientMsg {
+                version: 0,
+                data: ClientMessage::Population(PopulationEvent { amount: 4 }),
+            }
+        );
+        assert!(WSClientMsg::parse(r#"{"data":{"type":"launch","amount":4}}"#).is_err());
+        let error = WSClientMsg::parse(
+            r#"{"version":4,"data":{"type":"population","content":{"amount":4}}}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unsupported protocol version 4"));
+    }
+
+    #[test]
+    fn subscription_filters_topics() {
+        let mut subscription = WSSubscription::default();
+        assert!(subscription.accepts(WSTopic::Cargo));
+
+        subscription.subscribe(&[]);
+        assert!(subscription.accepts(WSTopic::Cargo));
+        subscription.subscribe(&[WSTopic::Weather]);
+        assert!(subscription.accepts(WSTopic::Weather));
+        assert!(!subscription.accepts(WSTopic::Cargo));
+        assert!(
+            subscription.accepts_message(&ServerMessage::Snapshot(SnapshotEvent {
+                weather: None,
+                population: None,
+                seconds_to_launch: None,
+                cargoes: Vec::new(),
+                news: Vec::new(),
+            }))
+        );
+
+        let mut subscription = WSSubscription::default();
+        subscription.unsubscribe(&[WSTopic::Cargo]);
+        assert!(!subscription.accepts(WSTopic::Cargo));
+        assert!(subscription.accepts(WSTopic::Launch));
+
+        let msg = WSClientMsg::parse(
+            r#"{"version":1,"data":{"type":"subscribe","content":["weather","population"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.data,
+            ClientMessage::Subscribe(vec![WSTopic::Weather, WSTopic::Population])
+        );
+
+        assert_eq!(
+            WSTopic::parse_list("weather, news"),
+            Ok(vec![WSTopic::Weather, WSTopic::News])
+        );
+        assert!(WSTopic::parse_list("weather,rocket").is_err());
+    }
+
+    #[test]
+    fn replay_buffer_resumes_or_resyncs() {
+        let launch = |amount| {
+            ServerMessage::Launch(LaunchEvent {
+                cargo_amount: amount,
+            })
+        };
+        let at = |seq| WSPosition { boot: 42, seq };
+        let mut buffer = WSReplayBuffer::new(3, 42);
+        assert_eq!(buffer.since(at(0)), Some(Vec::new()));
+
+        for amount in 1..=5 {
+            assert_eq!(buffer.push(launch(amount)).position(), at(amount));
+        }
+        assert_eq!(buffer.position(), at(5));
+
+        let missed: Vec<u32> = buffer
+            .since(at(2))
+            .unwrap()
+            .iter()
+            .map(|msg| msg.seq)
+            .collect();
+        assert_eq!(missed, vec![3, 4, 5]);
+        assert_eq!(buffer.since(at(5)), Some(Vec::new()));
+        // event 2 is gone
+        assert_eq!(buffer.since(at(1)), None);
+        assert_eq!(buffer.since(at(9)), None);
+        // numbered by a previous run, even though event 4 is kept
+        assert_eq!(buffer.since(WSPosition { boot: 7, seq: 4 }), None);
+    }
+
+    #[test]
+    fn position_as_event_id() {
+        let position = WSPosition { boot: 42, seq: 7 };
+        assert_eq!(position.to_string(), "42:7");
+        assert_eq!(WSPosition::parse(" 42:7"), Some(position));
+        assert_eq!(WSPosition::parse("7"), None);
+        assert_eq!(WSPosition::parse("42:x"), None);
     }
 }
@@ -1,16 +1,19 @@
+use model::enums::CargoType;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::error::Error;
 
 use reqwest::header::{HeaderMap, HeaderValue};
 
-const PROMPT: &str  = "
+const PROMPT: &str = "
  	<description>
   	這是一個寄往想像中未來外太空貿易站的貨物，收件人與寄件人可能是包含地球在內的任何外星生命，生成一段 120 字以內、不分段、用字通俗易懂的內容物說明，並為該物資取名。該說明將會收錄進一本太空物資圖鑑中。
-    請注意：你的回應必須完全符合格式要求，並使用繁體中文、避免使用中國用語，只包含名稱和說明，中間用%%%分隔，不要有任何其他文字。
+  	寄件人為這個貨物選擇的種類是「{cargo_type}」，請判斷圖畫內容最符合以下哪一個種類：water（水）、spring（彈簧）、stair（樓梯）、star（星星）、cake（蛋糕）、diamond（鑽石），並給出 0 到 1 之間的信心分數。
+    請注意：你的回應必須完全符合格式要求，並使用繁體中文、避免使用中國用語，只包含名稱、說明、種類和信心分數，中間用%%%分隔，不要有任何其他文字。
 	</description>
 
 	<output>
-		{{名稱}}%%%{{說明}}
+		{{名稱}}%%%{{說明}}%%%{{種類}}%%%{{信心分數}}
 	</output>
 ";
 
@@ -24,7 +27,48 @@ struct ClaudeResponse {
     content: Vec<ContentBlock>,
 }
 
-fn _gen_message_params(base64_image: &str) -> Value {
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeCheck {
+    pub suggested_type: CargoType,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CargoTextInfo {
+    pub name: String,
+    pub description: String,
+    pub type_check: Option<TypeCheck>,
+}
+
+fn gen_prompt(cargo_type: &CargoType) -> String {
+    let cargo_type = serde_json::to_value(cargo_type).unwrap();
+    PROMPT.replace("{cargo_type}", cargo_type.as_str().unwrap_or_default())
+}
+
+fn parse_text_info(text: &str) -> Option<CargoTextInfo> {
+    let mut splits = text.trim().split("%%%").map(str::trim);
+    let name = splits.next().filter(|name| !name.is_empty())?.to_owned();
+    let description = splits.next()?.to_owned();
+
+    let suggested_type = splits
+        .next()
+        .and_then(|t| serde_json::from_value(Value::String(t.to_lowercase())).ok());
+    let confidence = splits.next().and_then(|c| c.parse::<f32>().ok());
+    let type_check = suggested_type
+        .zip(confidence)
+        .map(|(suggested_type, confidence)| TypeCheck {
+            suggested_type,
+            confidence: confidence.clamp(0.0, 1.0),
+        });
+
+    Some(CargoTextInfo {
+        name,
+        description,
+        type_check,
+    })
+}
+
+fn gen_message_params(base64_image: &str, cargo_type: &CargoType) -> Value {
     json!({
         "model": "claude-3-5-sonnet-latest",
         "max_tokens": 1024,
@@ -32,10 +76,10 @@ fn _gen_message_params(base64_image: &str) -> Value {
             {
                 "role": "user",
                 "content": [
-                        {
-                            "type": "text",
-                            "text": PROMPT
-                        },
+                    {
+                        "type": "text",
+                        "text": gen_prompt(cargo_type)
+                    },
                     {
                         "type": "image",
                         "source": {
@@ -50,55 +94,256 @@ fn _gen_message_params(base64_image: &str) -> Value {
     })
 }
 
-pub async fn gen_cargo_text_info(
-    api_key: &str,
-    image_base64_url: &str,
-) -> Result<(String, String), reqwest::Error> {
-    let mut headers = HeaderMap::new();
-    headers.insert("x-api-key", HeaderValue::from_str(api_key).unwrap());
-    headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
-    headers.insert("content-type", HeaderValue::from_static("application/json"));
+pub type ClaudeError = Box<dyn Error + Send + Sync>;
 
-    // Construct the request body with multimodal content
-    let request_body = json!({
-        "model": "claude-3-5-sonnet-latest",
-        "max_tokens": 1024,
-        "messages": [
-            {
-                "role": "user",
-                "content": [
-                        {
-                            "type": "text",
-                            "text": PROMPT
-                        },
-                    {
-                        "type": "image",
-                        "source": {
-                            "type": "base64",
-                            "media_type": "image/jpeg",
-                            "data": image_base64_url
-                        }
-                    }
-                ]
-            }
-        ]
-    });
+/// A cargo to be described as part of a message batch
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub image_base64: String,
+    pub cargo_type: CargoType,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MessageBatch {
+    pub id: String,
+    pub processing_status: String,
+    pub results_url: Option<String>,
+}
+
+impl MessageBatch {
+    pub fn is_ended(&self) -> bool {
+        self.processing_status == "ended"
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResultLine {
+    custom_id: String,
+    result: BatchResultBody,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResultBody {
+    r#type: String,
+    message: Option<ClaudeResponse>,
+}
+
+#[derive(Debug)]
+pub struct BatchResult {
+    pub custom_id: String,
+    /// `None` when the request errored, expired, or the answer could not be parsed
+    pub text_info: Option<CargoTextInfo>,
+}
+
+fn get_response_text(response: &ClaudeResponse) -> &str {
+    response
+        .content
+        .first()
+        .map(|block| block.text.as_str())
+        .unwrap_or_default()
+}
+
+pub struct Claude {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl Claude {
+    pub fn new(base_url: &str, api_key: &str) -> Result<Self, ClaudeError> {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_str(api_key)?);
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder()
+                .default_headers(headers)
+                .build()?,
+        })
+    }
+
+    pub async fn gen_cargo_text_info(
+        &self,
+        image_base64_url: &str,
+        cargo_type: &CargoType,
+    ) -> Result<CargoTextInfo, ClaudeError> {
+        // Construct the request body with multimodal content
+        let request_body = gen_message_params(image_base64_url, cargo_type);
+
+        let response: ClaudeResponse = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let text = get_response_text(&response);
+        parse_text_info(text).ok_or_else(|| format!("unexpected response format: {text}").into())
+    }
+
+    pub async fn create_batch(
+        &self,
+        requests: &[BatchRequest],
+    ) -> Result<MessageBatch, ClaudeError> {
+        let requests: Vec<Value> = requests
+            .iter()
+            .map(|request| {
+                json!({
+                    "custom_id": request.custom_id,
+                    "params": gen_message_params(&request.image_base64, &request.cargo_type),
+                })
+            })
+            .collect();
+
+        Ok(self
+            .client
+            .post(format!("{}/v1/messages/batches", self.base_url))
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    pub async fn get_batch(&self, id: &str) -> Result<MessageBatch, ClaudeError> {
+        Ok(self
+            .client
+            .get(format!("{}/v1/messages/batches/{id}", self.base_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    pub async fn get_batch_results(
+        &self,
+        batch: &MessageBatch,
+    ) -> Result<Vec<BatchResult>, ClaudeError> {
+        let url = batch
+            .results_url
+            .as_deref()
+            .ok_or_else(|| format!("batch {} has no results yet", batch.id))?;
+
+        let body = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut results = Vec::new();
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            let BatchResultLine { custom_id, result } = serde_json::from_str(line)?;
+            let text_info = match (result.r#type.as_str(), result.message) {
+                ("succeeded", Some(message)) => parse_text_info(get_response_text(&message)),
+                _ => None,
+            };
+            results.push(BatchResult {
+                custom_id,
+                text_info,
+            });
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parse_text_info_with_type_check() {
+        let result = parse_text_info("星核鑽石%%%一顆會發光的鑽石。%%% Diamond %%%0.92").unwrap();
+        assert_eq!(result.name, "星核鑽石");
+        assert_eq!(result.description, "一顆會發光的鑽石。");
+        assert_eq!(
+            result.type_check,
+            Some(TypeCheck {
+                suggested_type: CargoType::Diamond,
+                confidence: 0.92,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_text_info_without_type_check() {
+        let result = parse_text_info("星核鑽石%%%一顆會發光的鑽石。").unwrap();
+        assert_eq!(result.type_check, None);
+        assert!(parse_text_info("沒有分隔符號").is_none());
+    }
+
+    async fn serve_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let app = Router::new()
+            .route(
+                "/v1/messages/batches",
+                post(|Json(body): Json<Value>| async move {
+                    assert_eq!(body["requests"][0]["custom_id"], "cargo-1");
+                    Json(json!({ "id": "msgbatch_1", "processing_status": "in_progress", "results_url": null }))
+                }),
+            )
+            .route(
+                "/v1/messages/batches/{id}",
+                get(|State(base_url): State<String>, Path(id): Path<String>| async move {
+                    Json(json!({
+                        "id": id,
+                        "processing_status": "ended",
+                        "results_url": format!("{base_url}/v1/messages/batches/{id}/results"),
+                    }))
+                }),
+            )
+            .route(
+                "/v1/messages/batches/{id}/results",
+                get(|| async {
+                    [
+                        json!({ "custom_id": "cargo-1", "result": { "type": "succeeded", "message": { "content": [{ "type": "text", "text": "星光膠囊%%%會發光的膠囊。%%%star%%%0.9" }] } } }),
+                        json!({ "custom_id": "cargo-2", "result": { "type": "errored" } }),
+                    ]
+                    .map(|line| line.to_string())
+                    .join("\n")
+                }),
+            )
+            .with_state(base_url.clone());
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base_url
+    }
 
-    // Create client and send request
-    let client = reqwest::Client::new();
+    #[tokio::test]
+    async fn batch_round_trip() {
+        let claude = Claude::new(&serve_stub().await, "test-key").unwrap();
 
-    let response: ClaudeResponse = client
-        .post("https://api.anthropic.com/v1/messages")
-        .headers(headers)
-        .json(&request_body)
-        .send()
-        .await?
-        .json()
-        .await?;
+        let batch = claude
+            .create_batch(&[BatchRequest {
+                custom_id: "cargo-1".to_string(),
+                image_base64: "aW1hZ2U=".to_string(),
+                cargo_type: CargoType::Star,
+            }])
+            .await
+            .unwrap();
+        assert!(!batch.is_ended());
 
-    // println!("{response:#?}");
+        let batch = claude.get_batch(&batch.id).await.unwrap();
+        assert!(batch.is_ended());
 
-    let text = response.content[0].text.clone();
-    let splits: Vec<&str> = text.split("%%%").collect();
-    Ok((splits[0].to_owned(), splits[1].to_owned()))
+        let results = claude.get_batch_results(&batch).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].custom_id, "cargo-1");
+        assert_eq!(results[0].text_info.as_ref().unwrap().name, "星光膠囊");
+        assert!(results[1].text_info.is_none());
+    }
 }
@@ -1,5 +1,25 @@
+use model::news::NewsRankParams;
+use model::news_source::WebDriverTarget;
 use project_root::get_project_root;
-use utils::env::get_env;
+use utils::env::{get_env, get_env_opt, get_env_or};
+
+/// What to do when the vision model thinks a drawing belongs to another cargo type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeCheckPolicy {
+    Off,
+    Flag,
+    Correct,
+}
+
+impl TypeCheckPolicy {
+    fn parse(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "off" => Self::Off,
+            "correct" => Self::Correct,
+            _ => Self::Flag,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -8,7 +28,43 @@ pub struct Config {
     pub port: u16,
     pub host: String,
     pub root_dir: String,
-    pub anthropic_api_key: String,
+    pub anthropic_api_key: Option<String>,
+    pub anthropic_base_url: String,
+    pub admin_token: Option<String>,
+    pub vision_max_edge: u32,
+    pub vision_jpeg_quality: u8,
+    /// encoded textures kept for retries, the oldest is evicted first
+    pub vision_cache_size: usize,
+    pub type_check_policy: TypeCheckPolicy,
+    pub type_check_threshold: f32,
+    pub text_blocklist: Vec<String>,
+    pub text_terms_path: Option<String>,
+    pub text_filter_retries: u32,
+    /// enabled news sources, `rss` and/or `webdriver`
+    pub news_sources: Vec<String>,
+    pub news_queries: Vec<String>,
+    /// feed url, `{query}` is replaced by each query
+    pub news_rss_url: String,
+    pub news_rank_params: NewsRankParams,
+    /// titles at least this similar are clustered into one story
+    pub news_similarity_threshold: f64,
+    pub news_webdriver_target: WebDriverTarget,
+    /// consecutive empty or failed news fetches before the status turns unhealthy
+    pub news_max_failures: u32,
+    /// keywords are counted over the news seen within this many days
+    pub news_keyword_window_days: i32,
+    pub news_keyword_limit: usize,
+    pub news_keyword_stopwords: Vec<String>,
+    /// weather providers tried in order, `cwa` and/or `page`
+    pub weather_providers: Vec<String>,
+    pub weather_station_id: String,
+    pub cwa_api_key: Option<String>,
+    /// the open data datastore, the dataset id is appended
+    pub cwa_api_url: String,
+    /// station page, `{station}` is replaced by the station id
+    pub weather_page_url: String,
+    /// broadcast events kept for clients resuming with `?boot=&since=`
+    pub ws_replay_size: usize,
 }
 
 impl Config {
@@ -17,7 +73,51 @@ impl Config {
         let wd_port = get_env("CHROMEDRIVER_PORT");
         let port = get_env("BACKEND_PORT");
         let host = get_env("BACKEND_HOST");
-        let anthropic_api_key = get_env("ANTHROPIC_API_KEY");
+        let anthropic_api_key = get_env_opt("ANTHROPIC_API_KEY");
+        let anthropic_base_url = get_env_or("ANTHROPIC_BASE_URL", "https://api.anthropic.com");
+        let vision_max_edge = get_env_or("VISION_MAX_EDGE", "768");
+        let vision_jpeg_quality = get_env_or("VISION_JPEG_QUALITY", "80");
+        let vision_cache_size = get_env_or("VISION_CACHE_SIZE", "64");
+        let type_check_policy = get_env_or("TYPE_CHECK_POLICY", "flag");
+        let type_check_threshold = get_env_or("TYPE_CHECK_THRESHOLD", "0.8");
+        let admin_token = get_env_opt("ADMIN_TOKEN");
+        let text_blocklist = get_env_or("TEXT_BLOCKLIST", "");
+        let text_terms_path = get_env_opt("TEXT_TERMS_PATH");
+        let text_filter_retries = get_env_or("TEXT_FILTER_RETRIES", "2");
+        let news_sources = get_env_or("NEWS_SOURCES", "webdriver");
+        let news_queries = get_env_or("NEWS_QUERIES", "太空");
+        let news_rss_url = get_env_or(
+            "NEWS_RSS_URL",
+            "https://news.google.com/rss/search?q={query}&hl=zh-TW&gl=TW&ceid=TW:zh-Hant",
+        );
+        let news_half_life_hours = get_env_or("NEWS_HALF_LIFE_HOURS", "24");
+        let news_recency_boost = get_env_or("NEWS_RECENCY_BOOST", "2");
+        let news_recency_window_hours = get_env_or("NEWS_RECENCY_WINDOW_HOURS", "6");
+        let news_similarity_threshold = get_env_or("NEWS_SIMILARITY_THRESHOLD", "0.6");
+        let news_webdriver_url = get_env_or(
+            "NEWS_WEBDRIVER_URL",
+            "https://news.google.com/search?q={query}&hl=zh-TW&gl=TW&ceid=TW%3Azh-Hant",
+        );
+        let news_section_selector = get_env_or("NEWS_SECTION_SELECTOR", "c-wiz .PO9Zff");
+        let news_title_selector = get_env_or("NEWS_TITLE_SELECTOR", ".JtKRv");
+        let news_publisher_selector = get_env_or("NEWS_PUBLISHER_SELECTOR", ".vr1PYe");
+        let news_time_selector = get_env_or("NEWS_TIME_SELECTOR", "time");
+        let news_max_failures = get_env_or("NEWS_MAX_FAILURES", "3");
+        let news_keyword_window_days = get_env_or("NEWS_KEYWORD_WINDOW_DAYS", "7");
+        let news_keyword_limit = get_env_or("NEWS_KEYWORD_LIMIT", "50");
+        let news_keyword_stopwords = get_env_or("NEWS_KEYWORD_STOPWORDS", "");
+        let weather_providers = get_env_or("WEATHER_PROVIDERS", "cwa,page");
+        let weather_station_id = get_env_or("WEATHER_STATION_ID", "C0C63");
+        let cwa_api_key = get_env_opt("CWA_API_KEY");
+        let cwa_api_url = get_env_or(
+            "CWA_API_URL",
+            "https://opendata.cwa.gov.tw/api/v1/rest/datastore",
+        );
+        let weather_page_url = get_env_or(
+            "WEATHER_PAGE_URL",
+            "https://www.cwa.gov.tw/V8/C/W/Observe/MOD/24hr/{station}.html",
+        );
+        let ws_replay_size = get_env_or("WS_REPLAY_SIZE", "500");
         let root_dir = get_root_dir();
 
         println!("configuration initialized: {host}:{port}, db: {database_url}, wd: {wd_port}, root: {root_dir}");
@@ -25,14 +125,68 @@ impl Config {
         Self {
             database_url,
             anthropic_api_key,
+            anthropic_base_url,
+            admin_token,
             port: port.parse().unwrap_or(3000),
             wd_port: wd_port.parse().unwrap_or(4000),
             host,
             root_dir,
+            vision_max_edge: vision_max_edge.parse().unwrap_or(768),
+            vision_jpeg_quality: vision_jpeg_quality.parse().unwrap_or(80),
+            vision_cache_size: vision_cache_size.parse().unwrap_or(64),
+            type_check_policy: TypeCheckPolicy::parse(&type_check_policy),
+            type_check_threshold: type_check_threshold.parse().unwrap_or(0.8),
+            text_blocklist: split_list(&text_blocklist),
+            text_terms_path,
+            text_filter_retries: text_filter_retries.parse().unwrap_or(2),
+            news_sources: split_list(&news_sources),
+            news_queries: split_list(&news_queries),
+            news_rss_url,
+            news_rank_params: NewsRankParams {
+                half_life_hours: parse_positive(&news_half_life_hours, 24.0),
+                recency_boost: news_recency_boost.parse().unwrap_or(2.0),
+                recency_window_hours: parse_positive(&news_recency_window_hours, 6.0),
+            },
+            news_similarity_threshold: parse_positive(&news_similarity_threshold, 0.6),
+            news_webdriver_target: WebDriverTarget {
+                url: news_webdriver_url,
+                section_selector: news_section_selector,
+                title_selector: news_title_selector,
+                publisher_selector: news_publisher_selector,
+                time_selector: news_time_selector,
+            },
+            news_max_failures: news_max_failures.parse().unwrap_or(3),
+            news_keyword_window_days: news_keyword_window_days.parse().unwrap_or(7),
+            news_keyword_limit: news_keyword_limit.parse().unwrap_or(50),
+            news_keyword_stopwords: split_list(&news_keyword_stopwords),
+            weather_providers: split_list(&weather_providers),
+            weather_station_id,
+            cwa_api_key,
+            cwa_api_url,
+            weather_page_url,
+            ws_replay_size: ws_replay_size.parse().unwrap_or(500),
         }
     }
 }
 
+/// Split a comma separated env value, skipping empty entries
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Divisors in the news score must not be zero
+fn parse_positive(value: &str, default: f64) -> f64 {
+    value
+        .parse()
+        .ok()
+        .filter(|value: &f64| *value > 0.0)
+        .unwrap_or(default)
+}
+
 fn get_root_dir() -> String {
     let path = get_project_root();
     if let Ok(mut path) = path {
@@ -1,18 +1,16 @@
-use crate::claude::gen_cargo_text_info;
+use crate::generator::{gen_and_update_cargo_text_info, poll_text_batches};
+use crate::news::fetch_news;
 use crate::state::AppState;
-use crate::weather::is_raining;
-use crate::webdriver::get_webdriver;
+use crate::weather::update_weather;
 use crate::ws_broadcast;
-use model::cargo::{Cargo, CargoTextInfoRequest};
-use model::news::News;
+use croner::Cron;
+use model::cargo::Cargo;
 use model::ws_msg::*;
-use std::fs;
-use thirtyfour::support::base64_encode;
+use sqlx::types::chrono::{DateTime, Utc};
 use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
 use tracing::{error, info};
 use utils::db::db_backup;
 use utils::runtime::rand_sleep;
-use uuid::Uuid;
 
 fn get_period(job_name: &str) -> &'static str {
     match job_name {
@@ -22,47 +20,24 @@ fn get_period(job_name: &str) -> &'static str {
         "fetch_remote_news" => "every 6 hours",
         "backup_database" => "every 8 hours",
         "gen_cargo_text_info" => "every 3 seconds",
+        "upgrade_procedural_text" => "every 10 minutes",
+        "poll_text_batches" => "every 1 minutes",
         "test_short" => "every 20 seconds",
         "test_long" => "every 1 minutes",
         _ => panic!("Unknown job name"),
     }
 }
 
-async fn gen_and_update_cargo_text_info_by_id(app_state: &AppState, id: Uuid) {
-    info!("start generating text info for {id}");
-    let img = fs::read(format!(
-        "{}/backend/db/storage/texture/{}.jpg",
-        app_state.config.root_dir.as_str(),
-        id.to_string()
-    ));
-
-    if let Ok(data) = img {
-        let _ = Cargo::set_pending_by_id(&app_state.pool, id, true).await;
-        let base64_img = base64_encode(&data);
-        let api_key = app_state.config.anthropic_api_key.as_str();
-        let result = gen_cargo_text_info(api_key, &base64_img).await;
-        match result {
-            Ok((name, description)) => {
-                info!("new text generated:\nname: {name}\ndescription: {description}");
-                let _ = Cargo::update_text_info(
-                    &app_state.pool,
-                    CargoTextInfoRequest {
-                        id,
-                        name,
-                        description,
-                    },
-                )
-                .await;
-                let _ = Cargo::set_pending_by_id(&app_state.pool, id, false).await;
-            }
-            Err(error) => {
-                let _ = Cargo::set_pending_by_id(&app_state.pool, id, false).await;
-                error!("failed to generate text info: {error:?}");
-            }
-        }
-    } else {
-        error!("failed to read cargo image");
-    }
+/// When the launch job runs next, read from the same schedule the scheduler uses
+pub fn get_next_launch(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let schedule = Job::schedule_to_cron(get_period("launch_rocket")).ok()?;
+    Cron::new(&schedule)
+        .with_seconds_required()
+        .with_dom_and_dow()
+        .parse()
+        .ok()?
+        .find_next_occurrence(&now, false)
+        .ok()
 }
 
 pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
@@ -74,7 +49,9 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
             Box::pin(async move {
                 info!("Launching rocket");
                 let amount = Cargo::launch(&pool).await;
-                let msg = WSMsg::launch(amount);
+                let msg = ServerMessage::Launch(LaunchEvent {
+                    cargo_amount: amount as u32,
+                });
                 ws_broadcast(msg, &sender);
             })
         }
@@ -88,7 +65,7 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
             Box::pin(async move {
                 let cargoes: Vec<Cargo> = Cargo::get_un_docs(&pool).await;
 
-                if cargoes.len() == 0 {
+                if cargoes.is_empty() {
                     return;
                 }
 
@@ -99,9 +76,40 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
                     cargoes.len()
                 );
 
-                let tasks: Vec<_> = cargo_ids
-                    .into_iter()
-                    .map(|id| gen_and_update_cargo_text_info_by_id(&app_state, id))
+                let tasks: Vec<_> = cargoes
+                    .iter()
+                    .map(|cargo| gen_and_update_cargo_text_info(&app_state, cargo))
+                    .collect();
+
+                futures::future::join_all(tasks).await;
+            })
+        }
+    })?;
+
+    let upgrade_procedural_text = Job::new_async(get_period("upgrade_procedural_text"), {
+        let app_state = app_state.clone();
+        move |_, _| {
+            let app_state = app_state.to_owned();
+            let pool = app_state.pool.clone();
+            Box::pin(async move {
+                if app_state.config.anthropic_api_key.is_none() {
+                    return;
+                }
+
+                let cargoes: Vec<Cargo> = Cargo::get_procedural(&pool, 10).await;
+
+                if cargoes.is_empty() {
+                    return;
+                }
+
+                info!(
+                    "upgrading {} cargoes with procedural text info",
+                    cargoes.len()
+                );
+
+                let tasks: Vec<_> = cargoes
+                    .iter()
+                    .map(|cargo| gen_and_update_cargo_text_info(&app_state, cargo))
                     .collect();
 
                 futures::future::join_all(tasks).await;
@@ -109,6 +117,16 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
         }
     })?;
 
+    let poll_text_batches = Job::new_async(get_period("poll_text_batches"), {
+        let app_state = app_state.clone();
+        move |_, _| {
+            let app_state = app_state.to_owned();
+            Box::pin(async move {
+                poll_text_batches(&app_state).await;
+            })
+        }
+    })?;
+
     let ship_cargoes = Job::new_async(get_period("ship_cargoes"), {
         let app_state = app_state.clone();
         move |_, _| {
@@ -123,23 +141,11 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
     let send_weather = Job::new_async(get_period("send_weather"), {
         let app_state = app_state.clone();
         move |_, _| {
-            let sender = app_state.ws_sender.clone();
+            let app_state = app_state.clone();
             Box::pin(async move {
                 rand_sleep(15000).await;
                 info!("Sending weather");
-                let raining = is_raining().await;
-                match raining {
-                    Ok(raining) => {
-                        info!("Is it raining outside? {raining}.");
-                        let msg = WSMsg::weather(raining);
-                        ws_broadcast(msg, &sender);
-                    }
-                    Err(error) => {
-                        error!("Failed to check weather: {error:?}");
-                        let msg = WSMsg::weather(false);
-                        ws_broadcast(msg, &sender);
-                    }
-                }
+                update_weather(&app_state).await;
             })
         }
     })?;
@@ -148,20 +154,11 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
         let app_state = app_state.clone();
 
         move |_, _| {
-            let pool = app_state.pool.clone();
+            let app_state = app_state.clone();
             Box::pin(async move {
-                info!("initializing webdriver");
-                let webdriver = get_webdriver(app_state.config.wd_port).await;
                 rand_sleep(30000).await;
                 info!("Fetching remote news");
-                if let Ok(driver) = webdriver {
-                    if let Err(err) = News::fetch_remote(&pool, &driver).await {
-                        error!("Failed to fetch remote news: {err:?}");
-                    };
-                    if driver.quit().await.is_err() {};
-                } else {
-                    error!("Failed to get webdriver {:?}", webdriver.err());
-                }
+                fetch_news(&app_state).await;
             })
         }
     })?;
@@ -196,8 +193,23 @@ pub async fn init(app_state: AppState) -> Result<(), JobSchedulerError> {
     sched.add(fetch_remote_news).await?;
     sched.add(backup_database).await?;
     sched.add(gen_cargo_text_info).await?;
+    sched.add(upgrade_procedural_text).await?;
+    sched.add(poll_text_batches).await?;
 
     // start scheduler
     sched.start().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_launch_is_within_the_period() {
+        let now: DateTime<Utc> = "2025-04-19T06:03:20Z".parse().unwrap();
+        let next_launch = get_next_launch(now).unwrap();
+        assert!(next_launch > now);
+        assert!((next_launch - now).num_seconds() <= 600);
+    }
+}
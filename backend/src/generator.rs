@@ -0,0 +1,436 @@
+use crate::claude::{BatchRequest, BatchResult, CargoTextInfo, Claude, TypeCheck};
+use crate::config::{Config, TypeCheckPolicy};
+use crate::procedural;
+use crate::state::AppState;
+use crate::text_filter::TextFilter;
+use model::cargo::{BackfillScope, Cargo, CargoTextInfoRequest};
+use model::enums::CargoTextSource;
+use model::text_batch::TextBatch;
+use std::error::Error;
+use std::fs;
+use thirtyfour::support::base64_encode;
+use tracing::{error, info, warn};
+use utils::texture::{compress_for_vision, dominant_color};
+use uuid::Uuid;
+
+/// How many cargoes go into one message batch
+const BATCH_SIZE: usize = 100;
+
+pub fn get_claude(config: &Config) -> Option<Claude> {
+    let api_key = config.anthropic_api_key.as_deref()?;
+    Claude::new(&config.anthropic_base_url, api_key)
+        .inspect_err(|error| error!("failed to create claude client: {error:?}"))
+        .ok()
+}
+
+fn get_texture_path(app_state: &AppState, id: Uuid) -> String {
+    format!(
+        "{}/backend/db/storage/texture/{id}.jpg",
+        app_state.config.root_dir.as_str(),
+    )
+}
+
+fn get_vision_payload(app_state: &AppState, id: Uuid) -> Result<String, Box<dyn Error>> {
+    if let Some(payload) = app_state.vision_cache.lock().unwrap().get(&id) {
+        return Ok(payload.clone());
+    }
+
+    let payload = encode_vision_payload(app_state, id)?;
+    app_state
+        .vision_cache
+        .lock()
+        .unwrap()
+        .insert(id, payload.clone());
+    Ok(payload)
+}
+
+/// The downscaled texture as base64, without going through the cache
+fn encode_vision_payload(app_state: &AppState, id: Uuid) -> Result<String, Box<dyn Error>> {
+    let data = fs::read(get_texture_path(app_state, id))?;
+    let compressed = compress_for_vision(
+        &data,
+        app_state.config.vision_max_edge,
+        app_state.config.vision_jpeg_quality,
+    )?;
+    info!(
+        "texture of {id} compressed from {} to {} bytes",
+        data.len(),
+        compressed.len()
+    );
+
+    Ok(base64_encode(&compressed))
+}
+
+/// Run generated text through the local filter, correcting what it can
+fn filter_text_info(
+    filter: &TextFilter,
+    text_info: CargoTextInfo,
+) -> Result<(CargoTextInfo, Vec<String>), String> {
+    let (name, mut corrections) = filter.apply(&text_info.name)?;
+    let (description, description_corrections) = filter.apply(&text_info.description)?;
+    corrections.extend(description_corrections);
+
+    Ok((
+        CargoTextInfo {
+            name,
+            description,
+            ..text_info
+        },
+        corrections,
+    ))
+}
+
+/// Generate text with the AI, regenerating when the filter rejects it.
+/// Errors with the last rejection reason, or `None` if the provider could not be used.
+async fn gen_ai_text_info(
+    app_state: &AppState,
+    claude: &Claude,
+    cargo: &Cargo,
+) -> Result<CargoTextInfo, Option<String>> {
+    let id = cargo.id;
+    let base64_img = match get_vision_payload(app_state, id) {
+        Ok(payload) => payload,
+        Err(error) => {
+            error!("failed to prepare cargo image: {error:?}");
+            return Err(None);
+        }
+    };
+
+    let mut rejections = Vec::new();
+    for attempt in 0..=app_state.config.text_filter_retries {
+        let text_info = match claude.gen_cargo_text_info(&base64_img, &cargo.r#type).await {
+            Ok(text_info) => text_info,
+            Err(error) => {
+                error!("failed to generate text info: {error:?}");
+                return Err(None);
+            }
+        };
+
+        match filter_text_info(&app_state.text_filter, text_info) {
+            Ok((text_info, corrections)) => {
+                app_state.vision_cache.lock().unwrap().remove(&id);
+                if !corrections.is_empty() || !rejections.is_empty() {
+                    let log = gen_filter_log(&rejections, &corrections);
+                    info!("filtered text info of {id}: {log}");
+                    let _ = Cargo::set_filter_log(&app_state.pool, id, &log).await;
+                }
+                return Ok(text_info);
+            }
+            Err(reason) => {
+                warn!("text info of {id} rejected on attempt {attempt}: {reason}");
+                rejections.push(reason);
+            }
+        }
+    }
+
+    let log = gen_filter_log(&rejections, &[]);
+    let _ = Cargo::set_filter_log(&app_state.pool, id, &log).await;
+    Err(rejections.pop())
+}
+
+/// A batch result filtered like `gen_ai_text_info`, errors with the rejection reason,
+/// or `None` if the request errored, expired or could not be parsed
+fn filter_batch_result(
+    filter: &TextFilter,
+    result: BatchResult,
+) -> Result<(CargoTextInfo, Vec<String>), Option<String>> {
+    let text_info = result.text_info.ok_or(None)?;
+    filter_text_info(filter, text_info).map_err(Some)
+}
+
+fn gen_filter_log(rejections: &[String], corrections: &[String]) -> String {
+    let mut log = Vec::new();
+    if !rejections.is_empty() {
+        log.push(format!("rejected: {}", rejections.join("; ")));
+    }
+    if !corrections.is_empty() {
+        log.push(format!("corrected: {}", corrections.join(", ")));
+    }
+    log.join(" | ")
+}
+
+fn gen_procedural_text_info(app_state: &AppState, cargo: &Cargo) -> CargoTextInfo {
+    let color = fs::read(get_texture_path(app_state, cargo.id))
+        .ok()
+        .and_then(|data| dominant_color(&data));
+    let (name, description) =
+        procedural::gen_cargo_text_info(cargo.id, &cargo.r#type, cargo.paint_time, color);
+    CargoTextInfo {
+        name,
+        description,
+        type_check: None,
+    }
+}
+
+async fn apply_type_check(app_state: &AppState, cargo: &Cargo, type_check: &TypeCheck) {
+    let TypeCheck {
+        suggested_type,
+        confidence,
+    } = type_check;
+    let id = cargo.id;
+    let _ = Cargo::update_type_check(&app_state.pool, id, suggested_type, *confidence).await;
+
+    if *suggested_type == cargo.r#type || *confidence < app_state.config.type_check_threshold {
+        return;
+    }
+
+    match app_state.config.type_check_policy {
+        TypeCheckPolicy::Off => {}
+        TypeCheckPolicy::Flag => {
+            let reason = format!(
+                "drawing looks like {suggested_type:?} instead of {:?} ({confidence:.2})",
+                cargo.r#type
+            );
+            info!("flagging cargo {id}: {reason}");
+            let _ = Cargo::set_flag(&app_state.pool, id, Some(&reason)).await;
+        }
+        TypeCheckPolicy::Correct => {
+            info!(
+                "correcting type of cargo {id} from {:?} to {suggested_type:?} ({confidence:.2})",
+                cargo.r#type
+            );
+            let _ = Cargo::set_type(&app_state.pool, id, suggested_type).await;
+        }
+    }
+}
+
+async fn save_text_info(
+    app_state: &AppState,
+    cargo: &Cargo,
+    text_info: CargoTextInfo,
+    source: CargoTextSource,
+) {
+    let CargoTextInfo {
+        name,
+        description,
+        type_check,
+    } = text_info;
+    info!("new text generated:\nname: {name}\ndescription: {description}");
+    let _ = Cargo::update_text_info(
+        &app_state.pool,
+        CargoTextInfoRequest {
+            id: cargo.id,
+            name,
+            description,
+        },
+        source,
+    )
+    .await;
+    if let Some(type_check) = type_check {
+        apply_type_check(app_state, cargo, &type_check).await;
+    }
+}
+
+/// Save the AI text info, or the reason it could not be used.
+/// Rejected text flags the cargo, a cargo without any text falls back to procedural text.
+async fn save_ai_text_info(
+    app_state: &AppState,
+    cargo: &Cargo,
+    ai_text_info: Result<CargoTextInfo, Option<String>>,
+) {
+    let id = cargo.id;
+    let (text_info, source) = match ai_text_info {
+        Ok(text_info) => (text_info, CargoTextSource::Ai),
+        Err(rejection) => {
+            if let Some(reason) = rejection {
+                let reason = format!("generated text rejected by filter: {reason}");
+                let _ = Cargo::set_flag(&app_state.pool, id, Some(&reason)).await;
+            }
+            // already has text, keep it until the AI is reachable again
+            if cargo.name.is_some() || cargo.description.is_some() {
+                return;
+            }
+            info!("falling back to procedural text info for {id}");
+            (
+                gen_procedural_text_info(app_state, cargo),
+                CargoTextSource::Procedural,
+            )
+        }
+    };
+    save_text_info(app_state, cargo, text_info, source).await;
+}
+
+pub async fn gen_and_update_cargo_text_info(app_state: &AppState, cargo: &Cargo) {
+    let id = cargo.id;
+    info!("start generating text info for {id}");
+    let _ = Cargo::set_pending_by_id(&app_state.pool, id, true).await;
+
+    let ai_text_info = match get_claude(&app_state.config) {
+        Some(claude) => gen_ai_text_info(app_state, &claude, cargo).await,
+        None => Err(None),
+    };
+
+    save_ai_text_info(app_state, cargo, ai_text_info).await;
+    let _ = Cargo::set_pending_by_id(&app_state.pool, id, false).await;
+}
+
+/// Submit every cargo in `scope` to the provider's batch interface, returns the created batches.
+/// The cargoes stay pending until `poll_text_batches` applies the results.
+pub async fn submit_text_batches(
+    app_state: &AppState,
+    scope: BackfillScope,
+) -> Result<Vec<TextBatch>, Box<dyn Error + Send + Sync>> {
+    let claude = get_claude(&app_state.config).ok_or("anthropic api key is not configured")?;
+    let cargoes = Cargo::get_for_backfill(&app_state.pool, scope).await;
+    info!("submitting {} cargoes for batch text info", cargoes.len());
+
+    let mut batches = Vec::new();
+    for chunk in cargoes.chunks(BATCH_SIZE) {
+        // claim the cargoes first so the regular job leaves them alone
+        let chunk_ids: Vec<Uuid> = chunk.iter().map(|cargo| cargo.id).collect();
+        Cargo::set_pending_by_ids(&app_state.pool, &chunk_ids, true).await?;
+
+        let mut requests = Vec::new();
+        for cargo in chunk {
+            // a backfill can cover every cargo, keep the payloads out of the cache
+            match encode_vision_payload(app_state, cargo.id) {
+                Ok(image_base64) => requests.push(BatchRequest {
+                    custom_id: cargo.id.to_string(),
+                    image_base64,
+                    cargo_type: cargo.r#type.clone(),
+                }),
+                Err(error) => error!("failed to prepare image of cargo {}: {error:?}", cargo.id),
+            }
+        }
+
+        if requests.is_empty() {
+            Cargo::set_pending_by_ids(&app_state.pool, &chunk_ids, false).await?;
+            continue;
+        }
+
+        let message_batch = match claude.create_batch(&requests).await {
+            Ok(message_batch) => message_batch,
+            Err(error) => {
+                Cargo::set_pending_by_ids(&app_state.pool, &chunk_ids, false).await?;
+                return Err(error);
+            }
+        };
+
+        info!(
+            "batch {} created with {} cargoes",
+            message_batch.id,
+            requests.len()
+        );
+        batches.push(TextBatch::create(&app_state.pool, &message_batch.id, &chunk_ids).await?);
+    }
+
+    Ok(batches)
+}
+
+/// Check the open batches and apply the results of the ones that ended
+pub async fn poll_text_batches(app_state: &AppState) {
+    let batches = TextBatch::get_open(&app_state.pool).await;
+    if batches.is_empty() {
+        return;
+    }
+
+    let Some(claude) = get_claude(&app_state.config) else {
+        return;
+    };
+
+    for batch in batches {
+        let message_batch = match claude.get_batch(&batch.id).await {
+            Ok(message_batch) => message_batch,
+            Err(error) => {
+                error!("failed to check batch {}: {error:?}", batch.id);
+                continue;
+            }
+        };
+        if !message_batch.is_ended() {
+            info!(
+                "batch {} is still {}",
+                batch.id, message_batch.processing_status
+            );
+            continue;
+        }
+
+        let results = match claude.get_batch_results(&message_batch).await {
+            Ok(results) => results,
+            Err(error) => {
+                error!("failed to fetch results of batch {}: {error:?}", batch.id);
+                continue;
+            }
+        };
+
+        let mut succeeded = 0;
+        for result in results {
+            let Ok(id) = Uuid::parse_str(&result.custom_id) else {
+                warn!(
+                    "unknown custom id {} in batch {}",
+                    result.custom_id, batch.id
+                );
+                continue;
+            };
+            let Some(cargo) = Cargo::get_by_id(&app_state.pool, id).await else {
+                continue;
+            };
+            // failed results are handled like a failed generation of the regular job
+            let ai_text_info = match filter_batch_result(&app_state.text_filter, result) {
+                Ok((text_info, corrections)) => {
+                    if !corrections.is_empty() {
+                        let log = gen_filter_log(&[], &corrections);
+                        let _ = Cargo::set_filter_log(&app_state.pool, id, &log).await;
+                    }
+                    succeeded += 1;
+                    Ok(text_info)
+                }
+                Err(Some(reason)) => {
+                    warn!("batch text info of {id} rejected: {reason}");
+                    let log = gen_filter_log(std::slice::from_ref(&reason), &[]);
+                    let _ = Cargo::set_filter_log(&app_state.pool, id, &log).await;
+                    Err(Some(reason))
+                }
+                Err(None) => {
+                    warn!("batch request of {id} errored, expired or could not be parsed");
+                    Err(None)
+                }
+            };
+            save_ai_text_info(app_state, &cargo, ai_text_info).await;
+        }
+        info!(
+            "batch {} applied, {succeeded}/{} succeeded",
+            batch.id,
+            batch.cargo_ids.len()
+        );
+
+        // failed cargoes are released so the regular job can pick them up again
+        let _ = Cargo::set_pending_by_ids(&app_state.pool, &batch.cargo_ids, false).await;
+        let _ = TextBatch::set_ended(&app_state.pool, &batch.id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(text_info: Option<(&str, &str)>) -> BatchResult {
+        BatchResult {
+            custom_id: Uuid::nil().to_string(),
+            text_info: text_info.map(|(name, description)| CargoTextInfo {
+                name: name.to_string(),
+                description: description.to_string(),
+                type_check: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn batch_result_filtered() {
+        let filter = TextFilter::new(vec![], vec![]);
+
+        let (text_info, corrections) =
+            filter_batch_result(&filter, result(Some(("星光膠囊", "这是膠囊。")))).unwrap();
+        assert_eq!(text_info.description, "這是膠囊。");
+        assert_eq!(corrections, vec!["这→這"]);
+
+        // an errored line, the cargo falls back like a failed generation
+        assert_eq!(
+            filter_batch_result(&filter, result(None)).unwrap_err(),
+            None
+        );
+        assert_eq!(
+            filter_batch_result(&filter, result(Some(("炸彈膠囊", "會爆炸。")))).unwrap_err(),
+            Some("blocked word 炸彈".to_string())
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use crate::state::AppState;
+use crate::weather::get_active_override;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use model::news_fetch_run::NewsFetchRun;
+use model::status::AdminStatus;
+use model::util::{ApiError, ApiResponse};
+use reqwest::StatusCode;
+
+/// Guard for the staff endpoints, closed until `ADMIN_TOKEN` is configured
+pub async fn require_admin(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = app_state.config.admin_token.as_deref() else {
+        tracing::warn!("ADMIN_TOKEN is not set, rejecting admin request");
+        return ApiError::new(StatusCode::SERVICE_UNAVAILABLE).into_response();
+    };
+    let provided = request
+        .headers()
+        .get("x-admin-token")
+        .and_then(|value| value.to_str().ok());
+    if provided != Some(token) {
+        return ApiError::new(StatusCode::UNAUTHORIZED).into_response();
+    }
+    next.run(request).await
+}
+
+pub async fn get_status(State(app_state): State<AppState>) -> Json<ApiResponse<AdminStatus>> {
+    let news = NewsFetchRun::get_health(&app_state.pool, app_state.config.news_max_failures).await;
+    ApiResponse::new_success(AdminStatus {
+        news,
+        weather_override: get_active_override(&app_state),
+    })
+    .into()
+}
@@ -1,15 +1,28 @@
 use crate::config::Config;
+use crate::generator::submit_text_batches;
 use crate::handlers::ws::ws_broadcast;
 use crate::state::AppState;
 use axum::extract::{Json, Path, State};
 use axum_typed_multipart::BaseMultipart;
 use model::cargo::*;
+use model::enums::{CargoTextSource, CargoType};
+use model::text_batch::TextBatch;
 use model::util::{ApiError, ApiResponse};
-use model::ws_msg::WSMsg;
+use model::ws_msg::{CargoEvent, ServerMessage};
 use reqwest::StatusCode;
 use sqlx::types::Uuid;
 use utils::texture::generate_texture;
 
+/// Where the displays find the texture of a cargo
+pub fn get_cargo_event(config: &Config, cargo_type: CargoType, id: &str) -> CargoEvent {
+    let Config { host, port, .. } = config;
+    CargoEvent {
+        cargo_type,
+        id: id.to_string(),
+        directory: format!("https://{host}:{port}/api/storage/texture/{id}.jpg"),
+    }
+}
+
 pub async fn get_cargoes(State(app_state): State<AppState>) -> Json<ApiResponse<Vec<Cargo>>> {
     ApiResponse::new_success(Cargo::get_20(&app_state.pool).await).into()
 }
@@ -20,18 +33,16 @@ pub async fn get_cargo_by_id(
 ) -> Json<ApiResponse<Cargo>> {
     let id = Uuid::parse_str(&id_str);
     if let Err(error) = id {
-        return ApiResponse::new_error_with_details(
+        ApiResponse::new_error_with_details(
             StatusCode::BAD_REQUEST,
             "Invalid UUID".to_owned(),
             Some(error.to_string()),
         )
-        .into();
+        .into()
+    } else if let Some(cargo) = Cargo::get_by_id(&app_state.pool, id.unwrap()).await {
+        ApiResponse::new_success(cargo).into()
     } else {
-        if let Some(cargo) = Cargo::get_by_id(&app_state.pool, id.unwrap()).await {
-            ApiResponse::new_success(cargo).into()
-        } else {
-            ApiResponse::new_error(StatusCode::NOT_FOUND).into()
-        }
+        ApiResponse::new_error(StatusCode::NOT_FOUND).into()
     }
 }
 
@@ -39,14 +50,73 @@ pub async fn get_today_cargoes(State(app_state): State<AppState>) -> Json<ApiRes
     ApiResponse::new_success(Cargo::get_today(&app_state.pool).await).into()
 }
 
+pub async fn get_flagged_cargoes(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<Vec<Cargo>>> {
+    ApiResponse::new_success(Cargo::get_flagged(&app_state.pool).await).into()
+}
+
+pub async fn clear_cargo_flag(
+    State(app_state): State<AppState>,
+    Path(id_str): Path<String>,
+) -> Json<ApiResponse<String>> {
+    match Uuid::parse_str(&id_str) {
+        Ok(id) => to_response(Cargo::set_flag(&app_state.pool, id, None).await),
+        Err(error) => ApiResponse::new_error_with_details(
+            StatusCode::BAD_REQUEST,
+            "Invalid UUID".to_owned(),
+            Some(error.to_string()),
+        )
+        .into(),
+    }
+}
+
+pub async fn submit_text_batch(
+    State(app_state): State<AppState>,
+    Json(request): Json<TextBatchRequest>,
+) -> Json<ApiResponse<Vec<TextBatch>>> {
+    match submit_text_batches(&app_state, request.scope).await {
+        Ok(batches) => ApiResponse::new_success(batches).into(),
+        Err(error) => ApiResponse::new_error_with_details(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error.to_string(),
+            None,
+        )
+        .into(),
+    }
+}
+
+pub async fn get_text_batches(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<Vec<TextBatch>>> {
+    ApiResponse::new_success(TextBatch::get_20(&app_state.pool).await).into()
+}
+
 pub async fn update_cargo_text_info(
     State(app_state): State<AppState>,
     Json(info): Json<CargoTextInfoRequest>,
 ) -> Json<ApiResponse<String>> {
-    if let Err(error) = Cargo::update_text_info(&app_state.pool, info).await {
-        ApiResponse::new_error_with_details(StatusCode::NOT_FOUND, error.to_string(), None).into()
-    } else {
-        ApiResponse::new_success("ok".to_string()).into()
+    to_response(Cargo::update_text_info(&app_state.pool, info, CargoTextSource::Manual).await)
+}
+
+fn to_response(result: Result<String, sqlx::Error>) -> Json<ApiResponse<String>> {
+    match result {
+        Ok(message) => ApiResponse::new_success(message).into(),
+        Err(sqlx::Error::RowNotFound) => ApiResponse::new_error_with_details(
+            StatusCode::NOT_FOUND,
+            "Cargo not found".to_owned(),
+            None,
+        )
+        .into(),
+        Err(error) => {
+            tracing::error!("failed to update cargo: {error:?}");
+            ApiResponse::new_error_with_details(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error.to_string(),
+                None,
+            )
+            .into()
+        }
     }
 }
 
@@ -75,15 +145,9 @@ pub async fn send_cargo(
 
     generate_texture(id, &file, &path);
 
-    let Config { host, port, .. } = app_state.config;
-
     ws_broadcast(
-        WSMsg::cargo(
-            cargo_type,
-            id,
-            &format!("https://{host}:{port}/api/storage/texture/{id}.jpg"),
-        ),
-        &app_state.ws_sender.clone(),
+        ServerMessage::Cargo(get_cargo_event(&app_state.config, cargo_type, id)),
+        &app_state.ws_sender,
     );
 
     ApiResponse::new_success(cargo).into()
@@ -0,0 +1,139 @@
+use crate::handlers::ws::{subscribe_events, WSQuery};
+use crate::state::AppState;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::StreamExt;
+use model::util::ApiResponse;
+use model::ws_msg::WSPosition;
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The WebSocket feed as Server-Sent Events, for players that cannot upgrade to WebSocket.
+///
+/// Every event is the same JSON as a WebSocket message, with `boot:seq` as the event id.
+/// A reconnecting `EventSource` resumes from `Last-Event-ID`, `?boot=&since=` works as well.
+pub async fn get_events(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<WSQuery>,
+) -> Response {
+    let parsed = query
+        .get_subscription()
+        .and_then(|subscription| get_since(&headers, &query).map(|since| (subscription, since)));
+    let (subscription, since) = match parsed {
+        Ok(parsed) => parsed,
+        Err(details) => {
+            return Json(ApiResponse::<()>::new_error_with_details(
+                StatusCode::BAD_REQUEST,
+                details,
+                None,
+            ))
+            .into_response();
+        }
+    };
+
+    let events =
+        subscribe_events(app_state, Arc::new(Mutex::new(subscription)), since).map(|message| {
+            Event::default()
+                .id(message.position().to_string())
+                .json_data(&message)
+        });
+
+    Sse::new(events)
+        .keep_alive(
+            KeepAlive::new()
+                .interval(Duration::from_secs(15))
+                .text("keep-alive"),
+        )
+        .into_response()
+}
+
+/// `Last-Event-ID` wins over the query, an id that does not parse,
+/// e.g. from an older server, is ignored
+fn get_since(headers: &HeaderMap, query: &WSQuery) -> Result<Option<WSPosition>, String> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(WSPosition::parse);
+    match last_event_id {
+        Some(position) => Ok(Some(position)),
+        None => query.get_since(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::WSChannel;
+    use axum::http::{HeaderValue, Uri};
+    use model::ws_msg::{LaunchEvent, PopulationEvent, ServerMessage};
+
+    fn query(uri: &str) -> WSQuery {
+        Query::try_from_uri(&uri.parse::<Uri>().unwrap()).unwrap().0
+    }
+
+    fn last_event_id(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("last-event-id", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn last_event_id_wins_over_the_query() {
+        let at = |boot, seq| Ok(Some(WSPosition { boot, seq }));
+        let resume = query("/events?boot=1&since=2");
+
+        assert_eq!(get_since(&last_event_id("7:9"), &resume), at(7, 9));
+        assert_eq!(get_since(&HeaderMap::new(), &resume), at(1, 2));
+        // a plain number from an older server
+        assert_eq!(get_since(&last_event_id("9"), &resume), at(1, 2));
+        assert_eq!(get_since(&HeaderMap::new(), &query("/events")), Ok(None));
+        assert!(get_since(&HeaderMap::new(), &query("/events?since=2")).is_err());
+    }
+
+    #[tokio::test]
+    async fn events_resume_from_last_event_id() {
+        let mut app_state = AppState::for_tests();
+        app_state.ws_sender = WSChannel::new(100, 10);
+        let launch = |amount| {
+            ServerMessage::Launch(LaunchEvent {
+                cargo_amount: amount,
+            })
+        };
+        let seen = app_state.ws_sender.send(launch(1));
+        app_state
+            .ws_sender
+            .send(ServerMessage::Population(PopulationEvent { amount: 3 }));
+        app_state.ws_sender.send(launch(2));
+
+        // the query would replay from before the first launch
+        let response = get_events(
+            State(app_state.clone()),
+            last_event_id(&seen.position().to_string()),
+            Query(query(&format!(
+                "/events?topics=launch&boot={}&since=0",
+                seen.boot
+            ))),
+        )
+        .await;
+        let mut body = response.into_body().into_data_stream();
+        let chunk = tokio::time::timeout(Duration::from_secs(2), body.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let event = String::from_utf8(chunk.to_vec()).unwrap();
+
+        assert!(event.contains(&format!("id: {}:3\n", seen.boot)));
+        let data = event
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap();
+        let msg: model::ws_msg::WSMsg = serde_json::from_str(data).unwrap();
+        assert_eq!(msg.data, launch(2));
+    }
+}
@@ -1,5 +1,18 @@
+pub mod admin;
 pub mod cargo;
+pub mod events;
 pub mod news;
 pub mod redirect;
 pub mod sys_info;
+pub mod weather;
 pub mod ws;
+
+use axum::extract::rejection::QueryRejection;
+use axum::extract::Query;
+
+/// A bad query becomes an error response instead of axum's plain text rejection
+fn parse_query<T>(query: Result<Query<T>, QueryRejection>) -> Result<T, String> {
+    query
+        .map(|Query(query)| query)
+        .map_err(|rejection| rejection.body_text())
+}
@@ -1,9 +1,205 @@
+use crate::handlers::parse_query;
+use crate::news::broadcast_news;
 use crate::state::AppState;
-use axum::extract::State;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query, State};
 use axum::Json;
-use model::news::News;
+use model::news::{
+    ManualNewsRequest, News, NewsKeyword, NewsPinRequest, NewsQuery, NewsSearchQuery,
+    NewsSearchResult,
+};
+use model::news_fetch_run::NewsFetchRun;
 use model::util::ApiResponse;
+use reqwest::StatusCode;
+use uuid::Uuid;
 
-pub async fn get_news(State(app_state): State<AppState>) -> Json<ApiResponse<Vec<String>>> {
-    ApiResponse::new_success(News::get_10(&app_state.pool).await).into()
+/// The ranked stories with their link, publisher and publish time
+pub async fn get_news_items(
+    State(app_state): State<AppState>,
+    query: Result<Query<NewsQuery>, QueryRejection>,
+) -> Json<ApiResponse<Vec<News>>> {
+    match parse_query(query) {
+        Ok(query) => ApiResponse::new_success(
+            News::get_10(
+                &app_state.pool,
+                query.rank.unwrap_or_default(),
+                &app_state.config.news_rank_params,
+            )
+            .await,
+        )
+        .into(),
+        Err(details) => ApiResponse::new_error_with_details(
+            StatusCode::BAD_REQUEST,
+            "Invalid news query".to_owned(),
+            Some(details),
+        )
+        .into(),
+    }
+}
+
+/// Plain titles for the ticker
+pub async fn get_news(
+    State(app_state): State<AppState>,
+    query: Result<Query<NewsQuery>, QueryRejection>,
+) -> Json<ApiResponse<Vec<String>>> {
+    match parse_query(query) {
+        Ok(query) => ApiResponse::new_success(
+            News::get_10_titles(
+                &app_state.pool,
+                query.rank.unwrap_or_default(),
+                &app_state.config.news_rank_params,
+            )
+            .await,
+        )
+        .into(),
+        Err(details) => ApiResponse::new_error_with_details(
+            StatusCode::BAD_REQUEST,
+            "Invalid news query".to_owned(),
+            Some(details),
+        )
+        .into(),
+    }
+}
+
+pub async fn search_news(
+    State(app_state): State<AppState>,
+    query: Result<Query<NewsSearchQuery>, QueryRejection>,
+) -> Json<ApiResponse<NewsSearchResult>> {
+    let query = match parse_query(query) {
+        Ok(query) => query,
+        Err(details) => {
+            return ApiResponse::new_error_with_details(
+                StatusCode::BAD_REQUEST,
+                "Invalid search query".to_owned(),
+                Some(details),
+            )
+            .into()
+        }
+    };
+
+    match News::search(&app_state.pool, query).await {
+        Ok(result) => ApiResponse::new_success(result).into(),
+        Err(error) => ApiResponse::new_error_with_details(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error.to_string(),
+            None,
+        )
+        .into(),
+    }
+}
+
+/// Trending keywords of the recent news, for the word cloud
+pub async fn get_news_keywords(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<Vec<NewsKeyword>>> {
+    let keywords = app_state.news_keywords.lock().unwrap().clone();
+    ApiResponse::new_success(keywords).into()
+}
+
+/// Rewrites of the same story that were merged into it
+pub async fn get_news_variants(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<Vec<News>>> {
+    ApiResponse::new_success(News::get_variants(&app_state.pool, id).await).into()
+}
+
+/// Statistics of the latest news fetches
+pub async fn get_news_runs(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<Vec<NewsFetchRun>>> {
+    ApiResponse::new_success(NewsFetchRun::get_20(&app_state.pool).await).into()
+}
+
+/// Moderation changes the ticker, push it to the clients
+async fn to_response(
+    app_state: &AppState,
+    result: Result<News, sqlx::Error>,
+) -> Json<ApiResponse<News>> {
+    match result {
+        Ok(news) => {
+            broadcast_news(app_state).await;
+            ApiResponse::new_success(news).into()
+        }
+        Err(sqlx::Error::RowNotFound) => ApiResponse::new_error_with_details(
+            StatusCode::NOT_FOUND,
+            "News not found".to_owned(),
+            None,
+        )
+        .into(),
+        Err(error) => {
+            tracing::error!("failed to update news: {error:?}");
+            ApiResponse::new_error_with_details(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error.to_string(),
+                None,
+            )
+            .into()
+        }
+    }
+}
+
+pub async fn create_manual_news(
+    State(app_state): State<AppState>,
+    Json(input): Json<ManualNewsRequest>,
+) -> Json<ApiResponse<News>> {
+    if input.title.trim().is_empty() {
+        return ApiResponse::new_error_with_details(
+            StatusCode::BAD_REQUEST,
+            "Title is empty".to_owned(),
+            None,
+        )
+        .into();
+    }
+    to_response(
+        &app_state,
+        News::create_manual(&app_state.pool, input).await,
+    )
+    .await
+}
+
+pub async fn hide_news(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<News>> {
+    to_response(
+        &app_state,
+        News::set_hidden(&app_state.pool, id, true).await,
+    )
+    .await
+}
+
+pub async fn unhide_news(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<News>> {
+    to_response(
+        &app_state,
+        News::set_hidden(&app_state.pool, id, false).await,
+    )
+    .await
+}
+
+/// Rewrites are never shown, pinning one pins the story it belongs to
+pub async fn pin_news(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(input): Json<NewsPinRequest>,
+) -> Json<ApiResponse<News>> {
+    to_response(
+        &app_state,
+        News::set_pinned_until(&app_state.pool, id, Some(input.until)).await,
+    )
+    .await
+}
+
+pub async fn unpin_news(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<News>> {
+    to_response(
+        &app_state,
+        News::set_pinned_until(&app_state.pool, id, None).await,
+    )
+    .await
 }
@@ -0,0 +1,88 @@
+use crate::handlers::parse_query;
+use crate::state::AppState;
+use crate::weather::{get_current_report, set_override};
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, State};
+use axum::Json;
+use model::util::ApiResponse;
+use model::weather::{
+    WeatherHistoryQuery, WeatherHourly, WeatherObservation, WeatherOverride, WeatherReport,
+};
+use reqwest::StatusCode;
+use sqlx::types::chrono::Utc;
+
+/// The weather to show, `stale` when the latest fetch failed
+pub async fn get_weather(State(app_state): State<AppState>) -> Json<ApiResponse<WeatherReport>> {
+    match get_current_report(&app_state) {
+        Some(report) => ApiResponse::new_success(report).into(),
+        None => ApiResponse::new_error_with_details(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no weather observation yet".to_string(),
+            None,
+        )
+        .into(),
+    }
+}
+
+/// Hourly aggregates of the configured station, for the season report
+pub async fn get_weather_history(
+    State(app_state): State<AppState>,
+    query: Result<Query<WeatherHistoryQuery>, QueryRejection>,
+) -> Json<ApiResponse<Vec<WeatherHourly>>> {
+    let range = parse_query(query).and_then(|query| query.get_range(Utc::now()));
+    let (from, to) = match range {
+        Ok(range) => range,
+        Err(details) => {
+            return ApiResponse::new_error_with_details(
+                StatusCode::BAD_REQUEST,
+                "Invalid history query".to_owned(),
+                Some(details),
+            )
+            .into()
+        }
+    };
+
+    match WeatherObservation::get_hourly(
+        &app_state.pool,
+        &app_state.config.weather_station_id,
+        from,
+        to,
+    )
+    .await
+    {
+        Ok(history) => ApiResponse::new_success(history).into(),
+        Err(error) => ApiResponse::new_error_with_details(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error.to_string(),
+            None,
+        )
+        .into(),
+    }
+}
+
+/// Force the weather until `until`, broadcast right away and once it expires
+pub async fn set_weather_override(
+    State(app_state): State<AppState>,
+    Json(input): Json<WeatherOverride>,
+) -> Json<ApiResponse<WeatherOverride>> {
+    if !input.is_active(Utc::now()) {
+        return ApiResponse::new_error_with_details(
+            StatusCode::BAD_REQUEST,
+            "the override must end in the future".to_string(),
+            None,
+        )
+        .into();
+    }
+
+    tracing::info!("weather overridden until {}: {input:?}", input.until);
+    set_override(&app_state, Some(input.clone()));
+    ApiResponse::new_success(input).into()
+}
+
+/// Back to the live weather
+pub async fn clear_weather_override(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<String>> {
+    set_override(&app_state, None);
+    ApiResponse::new_success("ok".to_string()).into()
+}
@@ -1,36 +1,220 @@
-use crate::state::AppState;
+use crate::cron::get_next_launch;
+use crate::handlers::cargo::get_cargo_event;
+use crate::state::{AppState, WSChannel};
+use crate::weather::get_current_report;
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{State, WebSocketUpgrade};
-use axum::response::Response;
-use futures::{SinkExt, StreamExt};
-use model::ws_msg::WSMsg;
+use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::response::{IntoResponse, Response};
+use futures::{stream, SinkExt, Stream, StreamExt};
+use model::cargo::Cargo;
+use model::enums::NewsRank;
+use model::news::News;
+use model::util::ApiError;
+use model::ws_msg::{
+    ClientMessage, ServerMessage, SnapshotEvent, WSClientMsg, WSMsg, WSPosition, WSSubscription,
+    WSTopic,
+};
+use reqwest::StatusCode;
 use serde::Deserialize;
-use tokio::sync::broadcast::Sender;
+use sqlx::types::chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
 
-pub async fn ws_handler(ws: WebSocketUpgrade, State(app_state): State<AppState>) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, app_state))
+#[derive(Deserialize)]
+pub struct WSQuery {
+    /// comma separated topics, every topic when missing or empty
+    topics: Option<String>,
+    /// the number of the last event received before reconnecting
+    since: Option<u32>,
+    /// the boot of the last event received, required along with `since`
+    boot: Option<u32>,
 }
 
-#[derive(Deserialize)]
-struct PopulationWsData {
-    r#type: String,
-    amount: u32,
+impl WSQuery {
+    pub fn get_subscription(&self) -> Result<WSSubscription, String> {
+        let topics = self
+            .topics
+            .as_deref()
+            .map(WSTopic::parse_list)
+            .transpose()?
+            .filter(|topics| !topics.is_empty());
+        Ok(WSSubscription::new(topics))
+    }
+
+    /// Where to resume, event numbers of another run mean nothing
+    pub fn get_since(&self) -> Result<Option<WSPosition>, String> {
+        match (self.boot, self.since) {
+            (Some(boot), Some(seq)) => Ok(Some(WSPosition { boot, seq })),
+            (None, Some(_)) => Err("since requires the boot of the event".to_string()),
+            _ => Ok(None),
+        }
+    }
 }
 
-#[derive(Deserialize)]
-struct PopulationWsMsg {
-    data: PopulationWsData,
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(app_state): State<AppState>,
+    Query(query): Query<WSQuery>,
+) -> Response {
+    let (subscription, since) = match query
+        .get_subscription()
+        .and_then(|subscription| query.get_since().map(|since| (subscription, since)))
+    {
+        Ok(parsed) => parsed,
+        Err(details) => {
+            tracing::warn!("rejected websocket: {details}");
+            return ApiError::new(StatusCode::BAD_REQUEST).into_response();
+        }
+    };
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state, subscription, since))
+}
+
+/// What a display would have seen by now, assembled from the caches and the database.
+/// Only the topics of the subscription are filled in, the rest is left empty
+pub async fn get_snapshot(state: &AppState, subscription: &WSSubscription) -> SnapshotEvent {
+    let now = Utc::now();
+    let mut snapshot = SnapshotEvent {
+        weather: None,
+        population: None,
+        seconds_to_launch: None,
+        cargoes: Vec::new(),
+        news: Vec::new(),
+    };
+    if subscription.accepts(WSTopic::Weather) {
+        snapshot.weather = get_current_report(state);
+    }
+    if subscription.accepts(WSTopic::Population) {
+        snapshot.population = state.population.lock().unwrap().clone();
+    }
+    if subscription.accepts(WSTopic::Launch) {
+        snapshot.seconds_to_launch =
+            get_next_launch(now).map(|next_launch| (next_launch - now).num_seconds().max(0) as u32);
+    }
+    if subscription.accepts(WSTopic::Cargo) {
+        snapshot.cargoes = Cargo::get_delivered(&state.pool)
+            .await
+            .into_iter()
+            .map(|cargo| get_cargo_event(&state.config, cargo.r#type, &cargo.id.to_string()))
+            .collect();
+    }
+    if subscription.accepts(WSTopic::News) {
+        snapshot.news = News::get_10(
+            &state.pool,
+            NewsRank::default(),
+            &state.config.news_rank_params,
+        )
+        .await;
+    }
+    snapshot
+}
+
+struct EventFeed {
+    state: AppState,
+    subscription: Arc<Mutex<WSSubscription>>,
+    /// `None` until subscribed, and again after lagging behind
+    rx: Option<Receiver<WSMsg>>,
+    /// the last event received, `None` on the first subscription
+    last: Option<WSPosition>,
+    pending: VecDeque<WSMsg>,
+}
+
+impl EventFeed {
+    /// Queue what the client is missing, the events after the last one when they are
+    /// still kept, a snapshot otherwise
+    async fn catch_up(&mut self) {
+        // subscribe before the snapshot is assembled, so nothing is missed in between
+        let (rx, position, missed) = self.state.ws_sender.subscribe(self.last);
+        self.rx = Some(rx);
+        let subscription = self.subscription.lock().unwrap().clone();
+        match (self.last, missed) {
+            (Some(_), Some(missed)) => self.pending.extend(missed),
+            (None, _) => self.pending.push_back(WSMsg::new(
+                position,
+                ServerMessage::Snapshot(get_snapshot(&self.state, &subscription).await),
+            )),
+            (Some(since), None) => {
+                tracing::info!("websocket events after {since} are gone, resyncing at {position}");
+                self.pending.push_back(WSMsg::new(
+                    position,
+                    ServerMessage::Resync(get_snapshot(&self.state, &subscription).await),
+                ));
+            }
+        }
+        self.last = Some(position);
+    }
+}
+
+/// The events a client subscribed to, starting with the ones it missed after `since`,
+/// or a snapshot for a new client. A client lagging behind catches up the same way
+/// instead of being dropped.
+pub fn subscribe_events(
+    state: AppState,
+    subscription: Arc<Mutex<WSSubscription>>,
+    since: Option<WSPosition>,
+) -> impl Stream<Item = WSMsg> + Send {
+    let feed = EventFeed {
+        state,
+        subscription,
+        rx: None,
+        last: since,
+        pending: VecDeque::new(),
+    };
+    stream::unfold(feed, |mut feed| async move {
+        loop {
+            if let Some(message) = feed.pending.pop_front() {
+                if feed
+                    .subscription
+                    .lock()
+                    .unwrap()
+                    .accepts_message(&message.data)
+                {
+                    return Some((message, feed));
+                }
+                continue;
+            }
+            let Some(rx) = feed.rx.as_mut() else {
+                feed.catch_up().await;
+                continue;
+            };
+            match rx.recv().await {
+                Ok(message) => {
+                    // already replayed, the last event is always of this run
+                    if feed.last.is_some_and(|last| message.seq <= last.seq) {
+                        continue;
+                    }
+                    feed.last = Some(message.position());
+                    feed.pending.push_back(message);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("event client lagged behind by {skipped} events");
+                    feed.rx = None;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
 }
 
-async fn handle_socket(socket: WebSocket, state: AppState) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: AppState,
+    subscription: WSSubscription,
+    since: Option<WSPosition>,
+) {
     let (mut sender, mut receiver) = socket.split();
-    let mut rx = state.ws_sender.subscribe();
+    let subscription = Arc::new(Mutex::new(subscription));
 
     // Spawn a task to forward broadcast messages to the WebSocket
-    tokio::spawn(async move {
-        while let Ok(message) = rx.recv().await {
-            if sender.send(message.into()).await.is_err() {
-                break; // Stop if the WebSocket connection is closed
+    tokio::spawn({
+        let events = subscribe_events(state.clone(), subscription.clone(), since);
+        async move {
+            let mut events = std::pin::pin!(events);
+            while let Some(message) = events.next().await {
+                if sender.send(message.to_json().into()).await.is_err() {
+                    break; // Stop if the WebSocket connection is closed
+                }
             }
         }
     });
@@ -38,16 +222,194 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
     while let Some(Ok(msg)) = receiver.next().await {
         tracing::info!("received websocket message: {msg:?}");
         if let Message::Text(text) = msg {
-            if let Ok(value) = serde_json::from_str::<PopulationWsMsg>(text.to_string().as_str()) {
-                if value.data.r#type == "population" {
-                    ws_broadcast(WSMsg::population(value.data.amount), &state.ws_sender);
-                }
+            match WSClientMsg::parse(text.as_str()) {
+                Ok(WSClientMsg { data, .. }) => match data {
+                    ClientMessage::Population(event) => {
+                        *state.population.lock().unwrap() = Some(event.clone());
+                        ws_broadcast(ServerMessage::Population(event), &state.ws_sender)
+                    }
+                    ClientMessage::Subscribe(topics) => {
+                        subscription.lock().unwrap().subscribe(&topics)
+                    }
+                    ClientMessage::Unsubscribe(topics) => {
+                        subscription.lock().unwrap().unsubscribe(&topics)
+                    }
+                },
+                Err(error) => tracing::warn!("invalid websocket message: {error}"),
             }
         }
     }
 }
 
-pub fn ws_broadcast(msg: String, sender: &Sender<String>) {
+pub fn ws_broadcast(msg: ServerMessage, sender: &WSChannel) {
+    let msg = sender.send(msg);
     tracing::info!("broadcasting websocket message: {msg:?}");
-    let _ = sender.send(msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::ws_msg::{LaunchEvent, PopulationEvent};
+
+    fn query(topics: Option<&str>) -> WSQuery {
+        WSQuery {
+            topics: topics.map(str::to_string),
+            since: None,
+            boot: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_of_the_subscribed_topics() {
+        let state = AppState::for_tests();
+        *state.population.lock().unwrap() = Some(PopulationEvent { amount: 7 });
+
+        // cargo and news would need the database
+        let subscription = WSSubscription::new(Some(vec![WSTopic::Population, WSTopic::Launch]));
+        let snapshot = get_snapshot(&state, &subscription).await;
+        assert_eq!(snapshot.population, Some(PopulationEvent { amount: 7 }));
+        assert!(snapshot.seconds_to_launch.is_some());
+
+        let subscription = WSSubscription::new(Some(vec![WSTopic::Weather]));
+        let snapshot = get_snapshot(&state, &subscription).await;
+        assert_eq!(snapshot.population, None);
+        assert_eq!(snapshot.seconds_to_launch, None);
+    }
+
+    fn launch(amount: u32) -> ServerMessage {
+        ServerMessage::Launch(LaunchEvent {
+            cargo_amount: amount,
+        })
+    }
+
+    /// Subscribed to launches only, a snapshot of them needs no database
+    fn launch_events(
+        state: &AppState,
+        since: Option<WSPosition>,
+    ) -> impl Stream<Item = WSMsg> + Send {
+        let subscription = WSSubscription::new(Some(vec![WSTopic::Launch]));
+        subscribe_events(state.clone(), Arc::new(Mutex::new(subscription)), since)
+    }
+
+    #[tokio::test]
+    async fn lagging_client_catches_up_from_the_replay() {
+        let mut state = AppState::for_tests();
+        state.ws_sender = WSChannel::new(2, 10);
+        let mut events = std::pin::pin!(launch_events(&state, None));
+
+        let snapshot = events.next().await.unwrap();
+        assert!(matches!(snapshot.data, ServerMessage::Snapshot(_)));
+        // more than the channel holds, the receiver lags
+        for amount in 1..=6 {
+            state.ws_sender.send(launch(amount));
+        }
+
+        let mut seqs = Vec::new();
+        for _ in 1..=6 {
+            let msg = events.next().await.unwrap();
+            assert_eq!(msg.data, launch(msg.seq));
+            seqs.push(msg.seq);
+        }
+        assert_eq!(seqs, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[tokio::test]
+    async fn lagging_client_resyncs_once_the_replay_is_gone() {
+        let mut state = AppState::for_tests();
+        state.ws_sender = WSChannel::new(2, 2);
+        let mut events = std::pin::pin!(launch_events(&state, None));
+
+        events.next().await.unwrap();
+        for amount in 1..=5 {
+            state.ws_sender.send(launch(amount));
+        }
+
+        let resync = events.next().await.unwrap();
+        assert!(matches!(resync.data, ServerMessage::Resync(_)));
+        assert_eq!(resync.seq, 5);
+        state.ws_sender.send(launch(6));
+        assert_eq!(events.next().await.unwrap().data, launch(6));
+    }
+
+    #[tokio::test]
+    async fn resume_from_another_boot_resyncs() {
+        let state = AppState::for_tests();
+        let sent = state.ws_sender.send(launch(1));
+        state.ws_sender.send(launch(2));
+
+        let since = WSPosition {
+            boot: sent.boot.wrapping_add(1),
+            seq: 1,
+        };
+        let mut events = std::pin::pin!(launch_events(&state, Some(since)));
+        let resync = events.next().await.unwrap();
+        assert!(matches!(resync.data, ServerMessage::Resync(_)));
+        assert_eq!(
+            resync.position(),
+            WSPosition {
+                boot: sent.boot,
+                seq: 2
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_replays_the_missed_events() {
+        let state = AppState::for_tests();
+        let seen = state.ws_sender.send(launch(1));
+        state.ws_sender.send(launch(2));
+        state.ws_sender.send(launch(3));
+
+        let mut events = std::pin::pin!(launch_events(&state, Some(seen.position())));
+        assert_eq!(events.next().await.unwrap().data, launch(2));
+        assert_eq!(events.next().await.unwrap().data, launch(3));
+        state.ws_sender.send(launch(4));
+        assert_eq!(events.next().await.unwrap().data, launch(4));
+    }
+
+    #[tokio::test]
+    async fn only_subscribed_topics_are_sent() {
+        let state = AppState::for_tests();
+        let subscription = Arc::new(Mutex::new(WSSubscription::new(Some(vec![
+            WSTopic::Population,
+        ]))));
+        let mut events =
+            std::pin::pin!(subscribe_events(state.clone(), subscription.clone(), None));
+        assert!(matches!(
+            events.next().await.unwrap().data,
+            ServerMessage::Snapshot(_)
+        ));
+
+        let population = ServerMessage::Population(PopulationEvent { amount: 3 });
+        state.ws_sender.send(launch(1));
+        state.ws_sender.send(population.clone());
+        assert_eq!(events.next().await.unwrap().data, population);
+
+        // subscribing later applies to the events still queued
+        state.ws_sender.send(launch(2));
+        subscription.lock().unwrap().subscribe(&[WSTopic::Launch]);
+        assert_eq!(events.next().await.unwrap().data, launch(2));
+    }
+
+    #[test]
+    fn subscription_from_query() {
+        for every_topic in [None, Some(""), Some(" , ")] {
+            let subscription = query(every_topic).get_subscription().unwrap();
+            assert_eq!(subscription, WSSubscription::default());
+        }
+
+        let subscription = query(Some("weather")).get_subscription().unwrap();
+        assert!(subscription.accepts(WSTopic::Weather));
+        assert!(!subscription.accepts(WSTopic::News));
+        assert!(query(Some("rocket")).get_subscription().is_err());
+
+        let mut resume = query(None);
+        resume.since = Some(4);
+        assert!(resume.get_since().is_err());
+        resume.boot = Some(42);
+        assert_eq!(
+            resume.get_since(),
+            Ok(Some(WSPosition { boot: 42, seq: 4 }))
+        );
+    }
 }
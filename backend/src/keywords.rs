@@ -0,0 +1,204 @@
+use jieba_rs::Jieba;
+use model::news::NewsKeyword;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tracing::info;
+
+/// Space words the default dictionary would split, e.g. 太空站 into 太空 and 站
+const SPACE_WORDS: &[&str] = &[
+    "太空站",
+    "太空人",
+    "太空船",
+    "太空梭",
+    "國際太空站",
+    "衛星",
+    "福衛八號",
+    "獵風者",
+    "火箭",
+    "星艦",
+    "獵鷹九號",
+    "推進器",
+    "登月",
+    "月球",
+    "火星",
+    "小行星",
+    "黑洞",
+    "銀河",
+    "望遠鏡",
+    "韋伯",
+    "外星人",
+    "太空中心",
+    "國家太空中心",
+];
+
+/// Words too common in headlines to say anything about the news
+const DEFAULT_STOPWORDS: &[&str] = &[
+    "今天",
+    "今日",
+    "昨天",
+    "明天",
+    "今年",
+    "去年",
+    "明年",
+    "目前",
+    "最新",
+    "首次",
+    "首度",
+    "成功",
+    "預計",
+    "可能",
+    "已經",
+    "正式",
+    "宣布",
+    "表示",
+    "指出",
+    "發現",
+    "發布",
+    "公布",
+    "完成",
+    "開始",
+    "計畫",
+    "計劃",
+    "進行",
+    "持續",
+    "相關",
+    "影響",
+    "問題",
+    "時間",
+    "一個",
+    "一次",
+    "這個",
+    "那個",
+    "我們",
+    "他們",
+    "你們",
+    "什麼",
+    "為什麼",
+    "如何",
+    "怎麼",
+    "還是",
+    "就是",
+    "不是",
+    "沒有",
+    "因為",
+    "所以",
+    "但是",
+    "而且",
+    "以及",
+    "或是",
+    "如果",
+    "還有",
+    "以上",
+    "以下",
+    "之後",
+    "之前",
+    "其中",
+    "報導",
+    "新聞",
+    "快訊",
+    "獨家",
+    "直擊",
+    "影片",
+    "圖輯",
+    "記者",
+    "中央社",
+    "自由時報",
+    "聯合報",
+];
+
+/// Segments news titles and counts hype-weighted keywords
+pub struct KeywordExtractor {
+    jieba: Jieba,
+    stopwords: HashSet<String>,
+}
+
+impl fmt::Debug for KeywordExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeywordExtractor")
+            .field("stopwords", &self.stopwords.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeywordExtractor {
+    /// `extra_stopwords` usually contains the news queries, every title matches them anyway
+    pub fn new(extra_stopwords: &[String]) -> Self {
+        let mut jieba = Jieba::new();
+        for word in SPACE_WORDS {
+            jieba.add_word(word, None, None);
+        }
+
+        let stopwords: HashSet<String> = DEFAULT_STOPWORDS
+            .iter()
+            .map(|word| word.to_string())
+            .chain(
+                extra_stopwords
+                    .iter()
+                    .map(|word| word.trim().to_lowercase()),
+            )
+            .filter(|word| !word.is_empty())
+            .collect();
+        info!(
+            "keyword extractor initialized with {} stopwords",
+            stopwords.len()
+        );
+
+        Self { jieba, stopwords }
+    }
+
+    fn is_keyword(&self, word: &str) -> bool {
+        word.chars().count() >= 2
+            && word.chars().any(char::is_alphabetic)
+            && !self.stopwords.contains(word)
+    }
+
+    /// Every title counts its hype plus one for each distinct keyword it contains,
+    /// returns the `limit` heaviest keywords.
+    pub fn extract(&self, titles: &[(String, i32)], limit: usize) -> Vec<NewsKeyword> {
+        let mut weights: HashMap<String, f64> = HashMap::new();
+        for (title, hype) in titles {
+            let words: HashSet<String> = self
+                .jieba
+                .cut(title, true)
+                .into_iter()
+                .map(|token| token.word.trim().to_lowercase())
+                .filter(|word| self.is_keyword(word))
+                .collect();
+            for word in words {
+                *weights.entry(word).or_default() += f64::from((*hype).max(0) + 1);
+            }
+        }
+
+        let mut keywords: Vec<NewsKeyword> = weights
+            .into_iter()
+            .map(|(word, weight)| NewsKeyword { word, weight })
+            .collect();
+        keywords.sort_by(|a, b| b.weight.total_cmp(&a.weight).then(a.word.cmp(&b.word)));
+        keywords.truncate(limit);
+        keywords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hype_weighted_keywords() {
+        let extractor = KeywordExtractor::new(&["太空".to_string()]);
+        let titles = vec![
+            ("SpaceX 星艦第八次試飛 成功回收超重型推進器".to_string(), 4),
+            ("星艦今天再度升空".to_string(), 0),
+            ("國際太空站迎來新一批補給".to_string(), 1),
+        ];
+        let keywords = extractor.extract(&titles, 5);
+
+        assert_eq!(keywords[0].word, "星艦");
+        assert_eq!(keywords[0].weight, 6.0);
+        assert!(keywords.iter().any(|keyword| keyword.word == "spacex"));
+        assert!(keywords.iter().all(|keyword| keyword.word != "成功"
+            && keyword.word != "今天"
+            && keyword.word != "太空"));
+        assert_eq!(keywords.len(), 5);
+    }
+}
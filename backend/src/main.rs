@@ -1,11 +1,15 @@
 mod claude;
 mod config;
 mod cron;
+mod generator;
 mod handlers;
+mod keywords;
+mod news;
+mod procedural;
 mod routes;
 mod state;
+mod text_filter;
 mod weather;
-mod webdriver;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -59,8 +63,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     .await
     .unwrap();
 
-    let socket_addr = SocketAddr::from(([0, 0, 0, 0], config.port.into()));
+    let socket_addr = SocketAddr::from(([0, 0, 0, 0], config.port));
 
+    tokio::spawn({
+        let app_state = app_state.clone();
+        async move { news::update_news_keywords(&app_state).await }
+    });
+    tokio::spawn({
+        let app_state = app_state.clone();
+        async move { weather::update_weather(&app_state).await }
+    });
     cron::init(app_state).await?;
 
     // axum::serve(listener, app.into_make_service()).await?;
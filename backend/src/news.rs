@@ -0,0 +1,82 @@
+use crate::config::Config;
+use crate::handlers::ws::ws_broadcast;
+use crate::state::AppState;
+use model::enums::NewsRank;
+use model::news::News;
+use model::news_fetch_run::NewsFetchRun;
+use model::news_source::{NewsSource, RssSource, WebDriverSource};
+use model::ws_msg::ServerMessage;
+use tracing::{error, info, warn};
+
+/// Build the configured news sources, one per source kind and query
+pub fn get_news_sources(config: &Config) -> Vec<Box<dyn NewsSource>> {
+    let mut sources: Vec<Box<dyn NewsSource>> = Vec::new();
+    for kind in &config.news_sources {
+        for query in &config.news_queries {
+            match kind.as_str() {
+                "rss" => sources.push(Box::new(RssSource::new(&config.news_rss_url, query))),
+                "webdriver" => sources.push(Box::new(WebDriverSource::new(
+                    config.wd_port,
+                    &config.news_webdriver_target,
+                    query,
+                ))),
+                _ => {
+                    warn!("unknown news source {kind}");
+                    break;
+                }
+            }
+        }
+    }
+    sources
+}
+
+/// Send the current ticker to every WebSocket client
+pub async fn broadcast_news(app_state: &AppState) {
+    let news = News::get_10(
+        &app_state.pool,
+        NewsRank::default(),
+        &app_state.config.news_rank_params,
+    )
+    .await;
+    ws_broadcast(ServerMessage::News(news), &app_state.ws_sender);
+}
+
+/// Fetch and store news from the configured sources, warn when fetching keeps failing
+pub async fn fetch_news(app_state: &AppState) {
+    let sources = get_news_sources(&app_state.config);
+    let result = News::fetch_remote(
+        &app_state.pool,
+        &sources,
+        app_state.config.news_similarity_threshold,
+    )
+    .await;
+
+    match result {
+        // bumped news may have been re-ranked as well
+        Ok(run) if run.inserted > 0 || run.bumped > 0 => broadcast_news(app_state).await,
+        Ok(_) => {}
+        Err(err) => error!("Failed to fetch remote news: {err:?}"),
+    }
+    update_news_keywords(app_state).await;
+
+    let health =
+        NewsFetchRun::get_health(&app_state.pool, app_state.config.news_max_failures).await;
+    if let Some(warning) = health.warning {
+        warn!("{warning}");
+    }
+}
+
+/// Recompute the trending keywords from the news within the window
+pub async fn update_news_keywords(app_state: &AppState) {
+    let titles =
+        News::get_recent_titles(&app_state.pool, app_state.config.news_keyword_window_days).await;
+    let extractor = app_state.keyword_extractor.clone();
+    let limit = app_state.config.news_keyword_limit;
+    // segmentation is CPU bound, keep it off the async workers
+    let keywords = tokio::task::spawn_blocking(move || extractor.extract(&titles, limit))
+        .await
+        .unwrap_or_default();
+
+    info!("{} news keywords extracted", keywords.len());
+    *app_state.news_keywords.lock().unwrap() = keywords;
+}
@@ -0,0 +1,186 @@
+use model::enums::CargoType;
+use uuid::Uuid;
+
+struct Words {
+    nouns: &'static [&'static str],
+    traits: &'static [&'static str],
+    uses: &'static [&'static str],
+}
+
+const SENDERS: &[&str] = &[
+    "寄件人來自獵戶座旋臂邊緣的小行星礦場",
+    "據說是土星環上的郵差親手打包",
+    "寄件人是一群住在月球背面的園丁",
+    "出貨單上只畫了一個會發光的笑臉",
+    "寄件人來自地球上一座靠海的小鎮",
+    "這是仙女座貿易站的老客戶固定寄出的貨物",
+];
+
+fn get_words(cargo_type: &CargoType) -> Words {
+    match cargo_type {
+        CargoType::Water => Words {
+            nouns: &["液態星河", "彗星露水", "深空泉水", "冰晶水囊"],
+            traits: &[
+                "搖晃時會發出海浪聲",
+                "在無重力下會自己聚成圓球",
+                "每喝一口都帶點星光的味道",
+            ],
+            uses: &[
+                "適合補給長途航行的太空人",
+                "可以用來澆灌太空溫室裡的植物",
+                "是外星旅人最常帶的伴手禮",
+            ],
+        },
+        CargoType::Spring => Words {
+            nouns: &["跳躍彈簧", "反重力發條", "彈跳線圈", "星際避震器"],
+            traits: &[
+                "壓下去會慢慢唱出一段旋律",
+                "能把小行星彈回原本的軌道",
+                "在低重力環境下彈得特別高",
+            ],
+            uses: &[
+                "常被裝在登陸艇的腳架上",
+                "是太空遊樂園最受歡迎的零件",
+                "修理舊太空船時一定用得到",
+            ],
+        },
+        CargoType::Stair => Words {
+            nouns: &["星梯", "折疊階梯", "雲端樓梯", "軌道步道"],
+            traits: &[
+                "每踩一階就會亮起一顆小星星",
+                "收起來只有火柴盒那麼大",
+                "可以一路延伸到空間站的頂樓",
+            ],
+            uses: &[
+                "讓太空人不用飛也能上下船艙",
+                "是行星之間搭橋時的必備材料",
+                "適合裝在重力很弱的小衛星上",
+            ],
+        },
+        CargoType::Star => Words {
+            nouns: &["迷你恆星", "星塵燈", "流星碎片", "星光膠囊"],
+            traits: &[
+                "在黑暗中會慢慢閃爍",
+                "摸起來暖暖的像剛曬過太陽",
+                "會隨著心情變換亮度",
+            ],
+            uses: &[
+                "可以照亮整條太空走廊",
+                "是外星小孩最喜歡的床頭燈",
+                "航行迷路時能指出回家的方向",
+            ],
+        },
+        CargoType::Cake => Words {
+            nouns: &["銀河蛋糕", "隕石千層派", "星雲鬆餅", "軌道甜甜圈"],
+            traits: &[
+                "切開後會飄出淡淡的星雲香氣",
+                "每一層都是不同星球的口味",
+                "放了一百年都不會壞",
+            ],
+            uses: &[
+                "是太空站慶祝生日的招牌點心",
+                "特別受到長途貨運駕駛的喜愛",
+                "適合和遠方的朋友一起分享",
+            ],
+        },
+        CargoType::Diamond => Words {
+            nouns: &["星核鑽石", "宇宙水晶", "引力寶石", "光年原石"],
+            traits: &[
+                "裡面封存著一小段星光",
+                "會把光折射成一道迷你極光",
+                "比小行星的岩層還要堅硬",
+            ],
+            uses: &[
+                "是星際貿易中最受信賴的貨幣",
+                "常被鑲在太空船的導航儀上",
+                "是外星王族送禮的首選",
+            ],
+        },
+    }
+}
+
+fn get_color_name([r, g, b]: [u8; 3]) -> &'static str {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    if max < 0.2 {
+        return "墨黑";
+    }
+    if delta < 0.12 {
+        return if max > 0.85 { "雪白" } else { "銀灰" };
+    }
+
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    match hue as u32 {
+        0..=14 | 345..=360 if max > 0.8 && min > 0.55 => "粉紅",
+        0..=14 | 345..=360 => "赤紅",
+        15..=39 if max < 0.6 => "棕褐",
+        15..=39 => "橘橙",
+        40..=69 => "金黃",
+        70..=159 => "翠綠",
+        160..=199 => "青藍",
+        200..=259 => "湛藍",
+        260..=299 => "紫羅蘭",
+        _ => "桃紅",
+    }
+}
+
+fn get_craft(paint_time: i32) -> &'static str {
+    match paint_time {
+        ..30 => "匆忙打包",
+        30..120 => "用心製作",
+        _ => "花了好長時間精雕細琢",
+    }
+}
+
+/// Build a name and a short description for a cargo without calling any remote service.
+/// The same cargo always gets the same text, so regenerating it is harmless.
+pub fn gen_cargo_text_info(
+    id: Uuid,
+    cargo_type: &CargoType,
+    paint_time: i32,
+    color: Option<[u8; 3]>,
+) -> (String, String) {
+    let seed = id.as_bytes();
+    let pick = |list: &[&'static str], n: usize| list[seed[n] as usize % list.len()];
+
+    let words = get_words(cargo_type);
+    let noun = pick(words.nouns, 0);
+    let color = color.map(get_color_name).unwrap_or("神秘");
+
+    let name = format!("{color}{noun}");
+    let description = format!(
+        "一件{}的{name}，{}，{}。{}。",
+        get_craft(paint_time),
+        pick(words.traits, 1),
+        pick(words.uses, 2),
+        pick(SENDERS, 3),
+    );
+
+    (name, description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_stable_and_short() {
+        let id = Uuid::parse_str("de426c3d-67b4-47c4-8648-8339b0f69c8f").unwrap();
+        let first = gen_cargo_text_info(id, &CargoType::Star, 45, Some([20, 40, 200]));
+        let second = gen_cargo_text_info(id, &CargoType::Star, 45, Some([20, 40, 200]));
+
+        assert_eq!(first, second);
+        assert!(first.0.starts_with("湛藍"));
+        assert!(first.1.chars().count() <= 120);
+    }
+}
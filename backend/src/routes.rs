@@ -1,11 +1,20 @@
+use crate::handlers::admin::{get_status, require_admin};
 use crate::handlers::cargo::*;
-use crate::handlers::news::get_news;
+use crate::handlers::events::get_events;
+use crate::handlers::news::{
+    create_manual_news, get_news, get_news_items, get_news_keywords, get_news_runs,
+    get_news_variants, hide_news, pin_news, search_news, unhide_news, unpin_news,
+};
 use crate::handlers::redirect;
 use crate::handlers::sys_info::get_temperature;
+use crate::handlers::weather::{
+    clear_weather_override, get_weather, get_weather_history, set_weather_override,
+};
 use crate::handlers::ws::ws_handler;
 use crate::state::AppState;
 use axum::extract::DefaultBodyLimit;
-use axum::routing::{any, get, post};
+use axum::middleware;
+use axum::routing::{any, delete, get, post};
 use axum::Router;
 use tower_http::compression::CompressionLayer;
 use tower_http::services::ServeDir;
@@ -24,9 +33,33 @@ pub fn get_routes(state: AppState) -> Router {
                         .route("/today", get(get_today_cargoes))
                         .route("/info", post(update_cargo_text_info)),
                 )
+                .nest(
+                    "/admin",
+                    Router::new()
+                        .route("/text-batch", post(submit_text_batch).get(get_text_batches))
+                        .route("/cargo/flagged", get(get_flagged_cargoes))
+                        .route("/cargo/{id}/flag", delete(clear_cargo_flag))
+                        .route("/news", post(create_manual_news))
+                        .route("/news/{id}/hide", post(hide_news).delete(unhide_news))
+                        .route("/news/{id}/pin", post(pin_news).delete(unpin_news))
+                        .route("/news-runs", get(get_news_runs))
+                        .route(
+                            "/weather/override",
+                            post(set_weather_override).delete(clear_weather_override),
+                        )
+                        .route("/status", get(get_status))
+                        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin)),
+                )
                 .route("/news", get(get_news))
+                .route("/news/items", get(get_news_items))
+                .route("/news/keywords", get(get_news_keywords))
+                .route("/news/search", get(search_news))
+                .route("/news/{id}/variants", get(get_news_variants))
                 .route("/sys-temp", get(get_temperature))
+                .route("/weather", get(get_weather))
+                .route("/weather/history", get(get_weather_history))
                 .route("/cargo-info", post(update_cargo_text_info))
+                .route("/events", get(get_events))
                 .nest(
                     "/render",
                     Router::new()
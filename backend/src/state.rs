@@ -1,21 +1,183 @@
 use crate::config::Config;
+use crate::keywords::KeywordExtractor;
+use crate::text_filter::TextFilter;
+use model::news::NewsKeyword;
+use model::weather::{WeatherOverride, WeatherReport};
+use model::ws_msg::{PopulationEvent, ServerMessage, WSMsg, WSPosition, WSReplayBuffer};
 use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
+use uuid::Uuid;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub pool: PgPool,
     pub config: Config,
-    pub ws_sender: broadcast::Sender<String>,
+    pub ws_sender: WSChannel,
+    /// base64 encoded, downscaled textures waiting to be described, keyed by cargo id
+    pub vision_cache: Arc<Mutex<VisionCache>>,
+    pub text_filter: Arc<TextFilter>,
+    pub keyword_extractor: Arc<KeywordExtractor>,
+    /// trending keywords, recomputed after each news fetch
+    pub news_keywords: Arc<Mutex<Vec<NewsKeyword>>>,
+    /// the last successful observation, `None` until the first fetch succeeds
+    pub weather: Arc<Mutex<Option<WeatherReport>>>,
+    /// forced by the staff, dropped once expired
+    pub weather_override: Arc<Mutex<Option<WeatherOverride>>>,
+    /// the last count of the visitor counter
+    pub population: Arc<Mutex<Option<PopulationEvent>>>,
+}
+
+/// Encoded textures by cargo id, the oldest entry is evicted once full,
+/// so textures that keep failing do not pile up
+#[derive(Debug, Default)]
+pub struct VisionCache {
+    capacity: usize,
+    payloads: HashMap<Uuid, String>,
+    order: VecDeque<Uuid>,
+}
+
+impl VisionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&String> {
+        self.payloads.get(id)
+    }
+
+    pub fn insert(&mut self, id: Uuid, payload: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.payloads.insert(id, payload).is_none() {
+            self.order.push_back(id);
+        }
+        while self.payloads.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.payloads.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if self.payloads.remove(id).is_some() {
+            self.order.retain(|cached| cached != id);
+        }
+    }
+}
+
+/// The WebSocket feed, every event is numbered and kept for clients resuming later
+#[derive(Clone, Debug)]
+pub struct WSChannel {
+    sender: broadcast::Sender<WSMsg>,
+    replay: Arc<Mutex<WSReplayBuffer>>,
+}
+
+impl WSChannel {
+    pub fn new(capacity: usize, replay_size: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+            replay: Arc::new(Mutex::new(WSReplayBuffer::new(
+                replay_size,
+                Uuid::new_v4().as_fields().0,
+            ))),
+        }
+    }
+
+    pub fn send(&self, data: ServerMessage) -> WSMsg {
+        // numbered and sent under the same lock, so receivers see the events in order
+        let mut replay = self.replay.lock().unwrap();
+        let msg = replay.push(data);
+        let _ = self.sender.send(msg.clone());
+        msg
+    }
+
+    /// A receiver for the coming events, the latest event
+    /// and the events after `since` if they are still kept
+    pub fn subscribe(
+        &self,
+        since: Option<WSPosition>,
+    ) -> (broadcast::Receiver<WSMsg>, WSPosition, Option<Vec<WSMsg>>) {
+        let replay = self.replay.lock().unwrap();
+        let missed = since.and_then(|since| replay.since(since));
+        (self.sender.subscribe(), replay.position(), missed)
+    }
 }
 
 impl AppState {
     pub fn new(pool: PgPool, config: Config) -> Self {
+        let text_filter =
+            TextFilter::from_config(&config.text_blocklist, config.text_terms_path.as_deref());
+        let stopwords: Vec<String> = config
+            .news_queries
+            .iter()
+            .chain(&config.news_keyword_stopwords)
+            .cloned()
+            .collect();
+        let keyword_extractor = KeywordExtractor::new(&stopwords);
+        let ws_sender = WSChannel::new(100, config.ws_replay_size);
+        let vision_cache_size = config.vision_cache_size;
         Self {
             pool,
             config,
-            ws_sender: broadcast::channel(100).0,
+            ws_sender,
+            vision_cache: Arc::new(Mutex::new(VisionCache::new(vision_cache_size))),
+            text_filter: Arc::new(text_filter),
+            keyword_extractor: Arc::new(keyword_extractor),
+            news_keywords: Arc::new(Mutex::new(Vec::new())),
+            weather: Arc::new(Mutex::new(None)),
+            weather_override: Arc::new(Mutex::new(None)),
+            population: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// A state for tests, the pool never connects and its queries fail after a second
+    pub fn for_tests() -> Self {
+        for (key, value) in [
+            ("DATABASE_URL", "postgres://localhost/unused"),
+            ("CHROMEDRIVER_PORT", "4444"),
+            ("BACKEND_PORT", "3000"),
+            ("BACKEND_HOST", "127.0.0.1"),
+        ] {
+            if std::env::var(key).is_err() {
+                std::env::set_var(key, value);
+            }
+        }
+        let config = Config::init();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_secs(1))
+            .connect_lazy(&config.database_url)
+            .unwrap();
+        Self::new(pool, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vision_cache_evicts_the_oldest() {
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut cache = VisionCache::new(2);
+        for id in &ids {
+            cache.insert(*id, id.to_string());
         }
+        assert_eq!(cache.get(&ids[0]), None);
+        assert_eq!(cache.get(&ids[2]), Some(&ids[2].to_string()));
+
+        cache.remove(&ids[1]);
+        cache.insert(ids[0], "again".to_string());
+        assert_eq!(cache.get(&ids[0]).map(String::as_str), Some("again"));
+        assert_eq!(cache.get(&ids[2]), Some(&ids[2].to_string()));
     }
 }
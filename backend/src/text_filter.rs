@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fs;
+use tracing::{error, info};
+
+/// Simplified characters with a single Traditional counterpart, converted automatically.
+/// Characters that are valid Traditional as well, like 胜 (胜肽) or 术 (白术), are left out.
+const SIMPLIFIED_PAIRS: &str =
+    "这這们們个個来來说說时時为為国國会會对對过過经經样樣东東车車长長门門问問间間学學进進\
+种種实實现現点點无無体體开開关關头頭与與见見电電机機气氣动動视視频頻话話语語读讀写寫买買卖賣乐樂\
+书書业業丽麗义義习習乡鄉亲親众眾优優传傳伤傷伟偉儿兒农農况況决決净淨刘劉则則刚剛创創别別剧劇\
+办辦务務劳勞势勢区區医醫华華单單卫衛厅廳压壓厂廠县縣参參双雙变變号號吗嗎员員响響园園围圍图圖\
+圆圓场場坏壞块塊坚堅备備够夠夹夾奋奮妈媽孙孫宝寶宁寧宽寬寻尋导導层層岁歲岛島币幣师師带帶帮幫广廣\
+庆慶应應废廢异異弹彈强強归歸当當录錄忆憶怀懷态態总總恋戀恶惡惊驚惯慣战戰扩擴扫掃执執护護报報担擔\
+拥擁择擇挤擠挥揮换換损損据據摄攝数數断斷旧舊显顯晒曬杀殺权權条條杨楊极極构構枪槍标標树樹桥橋\
+梦夢检檢楼樓欢歡汉漢汤湯沟溝没沒泪淚泽澤洁潔浅淺测測济濟浓濃涨漲温溫湾灣湿濕满滿滚滾灯燈灵靈灭滅\
+灾災炉爐热熱爱愛爷爺牵牽犹猶独獨狮獅猎獵环環玛瑪疗療监監盖蓋盘盤确確码碼础礎礼禮离離积積称稱稳穩\
+穷窮竞競笔筆笼籠类類粮糧紧緊红紅约約级級纪紀纯純纸紙线線练練组組细細织織终終结結绕繞给給络絡绝絕\
+统統继繼绩績绪緒续續维維绿綠缓緩编編缘緣网網罗羅职職联聯聪聰肠腸肤膚脑腦脚腳脸臉节節艺藝药藥\
+营營虽雖虫蟲蚁蟻补補装裝观觀规規览覽觉覺计計认認让讓训訓议議记記讲講许許论論设設访訪证證评評识識\
+诉訴词詞译譯试試诗詩该該详詳请請课課谁誰调調谈談谢謝贝貝负負贡貢财財责責败敗货貨质質贵貴费費贴貼\
+贸貿资資赏賞赛賽赶趕跃躍轨軌转轉轮輪软軟轻輕载載较較辆輛边邊达達迁遷运運还還远遠违違连連迟遲\
+选選递遞邮郵邻鄰释釋针針钢鋼钱錢铁鐵银銀链鏈销銷锁鎖错錯锅鍋键鍵镜鏡闭閉闪閃闹鬧闻聞阅閱队隊阳陽\
+阴陰阵陣阶階际際陆陸陈陳险險随隨隐隱难難雾霧静靜页頁顶頂项項顺順顾顧预預领領题題颜顏额額风風飞飛\
+饭飯饮飲饰飾饱飽馆館马馬驾駕验驗骑騎鱼魚鸟鳥鸡雞麦麥龙龍龟龜齐齊齿齒团團";
+
+/// Simplified characters that map to more than one Traditional character, the text has to be regenerated.
+/// Characters that are valid Traditional as well, like 后, 干 and 云, are left out.
+const AMBIGUOUS_SIMPLIFIED: &str = "发复历钟冲尽获汇脏签纤须";
+
+/// Mainland terms and their Taiwanese equivalents, written in Traditional characters.
+/// Terms that are also correct in Taiwan, like 質量 (mass) or 程序 (procedure), are left out,
+/// as are terms that depend on the context, like 土豆 or 智能, add those to `TEXT_TERMS_PATH`.
+const DEFAULT_TERMS: &[(&str, &str)] = &[
+    ("視頻", "影片"),
+    ("軟件", "軟體"),
+    ("硬件", "硬體"),
+    ("網絡", "網路"),
+    ("默認", "預設"),
+    ("激光", "雷射"),
+    ("打印", "列印"),
+    ("屏幕", "螢幕"),
+    ("數據", "資料"),
+    ("鼠標", "滑鼠"),
+    ("硬盤", "硬碟"),
+    ("短信", "簡訊"),
+    ("攝像頭", "攝影機"),
+    ("出租車", "計程車"),
+    ("自行車", "腳踏車"),
+    ("酸奶", "優格"),
+    ("菠蘿", "鳳梨"),
+    ("三文魚", "鮭魚"),
+    ("宇航員", "太空人"),
+    ("航天員", "太空人"),
+    ("互聯網", "網際網路"),
+];
+
+const DEFAULT_BLOCKLIST: &[&str] = &[
+    "血腥",
+    "屍體",
+    "毒品",
+    "賭博",
+    "色情",
+    "自殺",
+    "殺人",
+    "槍枝",
+    "炸彈",
+    "恐怖攻擊",
+];
+
+#[derive(Debug)]
+pub struct TextFilter {
+    simplified: HashMap<char, char>,
+    terms: Vec<(String, String)>,
+    blocklist: Vec<String>,
+}
+
+impl TextFilter {
+    pub fn new(extra_terms: Vec<(String, String)>, extra_blocklist: Vec<String>) -> Self {
+        let chars: Vec<char> = SIMPLIFIED_PAIRS.chars().collect();
+        let simplified: HashMap<char, char> =
+            chars.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+
+        let mut filter = Self {
+            simplified,
+            terms: Vec::new(),
+            blocklist: Vec::new(),
+        };
+
+        // configured tables may be written in Simplified characters, normalise them first
+        let terms = DEFAULT_TERMS
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .chain(extra_terms)
+            .map(|(from, to)| (filter.to_traditional(&from), to))
+            .filter(|(from, to)| !from.is_empty() && from != to)
+            .collect();
+        let blocklist = DEFAULT_BLOCKLIST
+            .iter()
+            .map(|word| word.to_string())
+            .chain(extra_blocklist)
+            .map(|word| filter.to_traditional(word.trim()))
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        filter.terms = terms;
+        filter.blocklist = blocklist;
+        filter
+    }
+
+    /// Build the filter from the configured blocklist and an optional `from,to` term table file
+    pub fn from_config(blocklist: &[String], terms_path: Option<&str>) -> Self {
+        let terms = terms_path
+            .and_then(|path| {
+                fs::read_to_string(path)
+                    .inspect_err(|err| error!("failed to read term table {path}: {err:?}"))
+                    .ok()
+            })
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                    .filter_map(|line| line.split_once(','))
+                    .map(|(from, to)| (from.trim().to_string(), to.trim().to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let filter = Self::new(terms, blocklist.to_vec());
+        info!(
+            "text filter initialized with {} terms and {} blocked words",
+            filter.terms.len(),
+            filter.blocklist.len()
+        );
+        filter
+    }
+
+    fn to_traditional(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| *self.simplified.get(&c).unwrap_or(&c))
+            .collect()
+    }
+
+    /// Correct what can be corrected, returns the cleaned text with the applied corrections,
+    /// or the reason the text has to be rejected.
+    pub fn apply(&self, text: &str) -> Result<(String, Vec<String>), String> {
+        let mut corrections = Vec::new();
+
+        let mut converted: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if AMBIGUOUS_SIMPLIFIED.contains(c) {
+                return Err(format!("ambiguous simplified character {c}"));
+            }
+            match self.simplified.get(&c) {
+                Some(traditional) => {
+                    let correction = format!("{c}→{traditional}");
+                    if !corrections.contains(&correction) {
+                        corrections.push(correction);
+                    }
+                    converted.push(*traditional);
+                }
+                None => converted.push(c),
+            }
+        }
+        let mut text: String = converted.into_iter().collect();
+
+        for (from, to) in &self.terms {
+            if text.contains(from.as_str()) {
+                text = text.replace(from.as_str(), to);
+                corrections.push(format!("{from}→{to}"));
+            }
+        }
+
+        if let Some(word) = self
+            .blocklist
+            .iter()
+            .find(|word| text.contains(word.as_str()))
+        {
+            return Err(format!("blocked word {word}"));
+        }
+
+        Ok((text, corrections))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrects_simplified_and_terms() {
+        let filter = TextFilter::new(vec![("视频".to_string(), "影片".to_string())], vec![]);
+        let (text, corrections) = filter.apply("这是一段太空视频").unwrap();
+
+        assert_eq!(text, "這是一段太空影片");
+        assert_eq!(corrections, vec!["这→這", "视→視", "频→頻", "視頻→影片"]);
+    }
+
+    #[test]
+    fn rejects_blocked_and_ambiguous_text() {
+        let filter = TextFilter::new(vec![], vec!["外星怪獸".to_string()]);
+
+        assert!(filter.apply("一隻外星怪獸").is_err());
+        assert!(filter.apply("出发前往月球").is_err());
+        assert!(filter.apply("一顆會發光的星星").unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn keeps_valid_traditional_text() {
+        let filter = TextFilter::from_config(&[], None);
+        for text in [
+            "皇后與后羿人云亦云，測量若干星體的質量，照程序完成項目",
+            "胜肽與白术，葉价適中",
+            "智能障礙的朋友在田裡種土豆，交換信息",
+        ] {
+            assert_eq!(filter.apply(text).unwrap(), (text.to_string(), vec![]));
+        }
+    }
+}
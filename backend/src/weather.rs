@@ -1,15 +1,253 @@
-use scraper::{Html, Selector};
-use std::error::Error;
-
-const URL: &str = "https://www.cwa.gov.tw/V8/C/W/Observe/MOD/24hr/C0C63.html";
-
-pub async fn is_raining() -> Result<bool, Box<dyn Error>> {
-    let html = reqwest::get(URL).await?.text().await?;
-    let fragment = Html::parse_fragment(&html);
-    let selector = Selector::parse("img")?;
-    if let Some(first_image) = fragment.select(&selector).next() {
-        Ok(first_image.html().contains("雨"))
-    } else {
-        Ok(false)
+use crate::config::Config;
+use crate::handlers::ws::ws_broadcast;
+use crate::state::AppState;
+use model::weather::{WeatherError, WeatherObservation, WeatherOverride, WeatherReport};
+use model::weather_provider::{CwaApiProvider, CwaPageProvider, WeatherProvider};
+use model::ws_msg::ServerMessage;
+use sqlx::types::chrono::Utc;
+use tracing::{error, info, warn};
+
+pub fn get_weather_providers(config: &Config) -> Vec<Box<dyn WeatherProvider>> {
+    let mut providers: Vec<Box<dyn WeatherProvider>> = Vec::new();
+    for kind in &config.weather_providers {
+        match kind.as_str() {
+            "cwa" => match &config.cwa_api_key {
+                Some(api_key) => providers.push(Box::new(CwaApiProvider::new(
+                    &config.cwa_api_url,
+                    api_key,
+                    &config.weather_station_id,
+                ))),
+                None => warn!("CWA_API_KEY is not set, skipping the cwa weather provider"),
+            },
+            "page" => providers.push(Box::new(CwaPageProvider::new(
+                &config.weather_page_url,
+                &config.weather_station_id,
+            ))),
+            _ => warn!("unknown weather provider {kind}"),
+        }
+    }
+    providers
+}
+
+/// The observation of the first provider that succeeds, later providers are fallbacks
+pub async fn fetch_observation(
+    providers: &[Box<dyn WeatherProvider>],
+) -> Result<WeatherObservation, WeatherError> {
+    let mut last_error: WeatherError = "no weather provider configured".into();
+    for provider in providers {
+        match provider.fetch().await {
+            Ok(observation) => return Ok(observation),
+            Err(error) => {
+                warn!("weather provider {} failed: {error}", provider.name());
+                last_error = error;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// The latest observation of the configured station
+pub async fn get_observation(config: &Config) -> Result<WeatherObservation, WeatherError> {
+    fetch_observation(&get_weather_providers(config)).await
+}
+
+/// The staff override, if it hasn't expired yet
+pub fn get_active_override(app_state: &AppState) -> Option<WeatherOverride> {
+    let mut weather_override = app_state.weather_override.lock().unwrap();
+    if weather_override
+        .as_ref()
+        .is_some_and(|weather_override| !weather_override.is_active(Utc::now()))
+    {
+        info!("weather override expired");
+        *weather_override = None;
+    }
+    weather_override.clone()
+}
+
+/// The weather to show, an active override replaces the live observation
+pub fn get_current_report(app_state: &AppState) -> Option<WeatherReport> {
+    let live = app_state.weather.lock().unwrap().clone();
+    match get_active_override(app_state) {
+        Some(weather_override) => Some(WeatherReport {
+            observation: weather_override.apply(live.as_ref().map(|report| &report.observation)),
+            fetched_at: Utc::now(),
+            stale: false,
+            overridden: true,
+        }),
+        None => live,
+    }
+}
+
+/// Replace or clear the staff override, the weather is broadcast right away
+/// and again once the override expires, so the screens go back to the live weather
+pub fn set_override(app_state: &AppState, weather_override: Option<WeatherOverride>) {
+    *app_state.weather_override.lock().unwrap() = weather_override.clone();
+    broadcast_weather(app_state);
+
+    let Some(weather_override) = weather_override else {
+        return;
+    };
+    let app_state = app_state.clone();
+    tokio::spawn(async move {
+        let remaining = (weather_override.until - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        tokio::time::sleep(remaining).await;
+        // a newer override has its own timer
+        let current = app_state.weather_override.lock().unwrap().clone();
+        if current.as_ref() == Some(&weather_override) {
+            broadcast_weather(&app_state);
+        }
+    });
+}
+
+pub fn broadcast_weather(app_state: &AppState) {
+    match get_current_report(app_state) {
+        Some(report) => ws_broadcast(ServerMessage::Weather(report), &app_state.ws_sender),
+        None => warn!("no weather observation to broadcast yet"),
+    }
+}
+
+/// Fetch and broadcast the weather, a failed fetch rebroadcasts the last observation as stale
+pub async fn update_weather(app_state: &AppState) {
+    let result = get_observation(&app_state.config).await;
+    let observation = {
+        let mut weather = app_state.weather.lock().unwrap();
+        match result {
+            Ok(observation) => {
+                info!(
+                    "Weather outside: {:?}, wind {:?}, rain {:?}.",
+                    observation.condition, observation.wind_level, observation.rain_level
+                );
+                *weather = Some(WeatherReport::new(observation.clone()));
+                Some(observation)
+            }
+            Err(error) => {
+                error!("Failed to check weather: {error:?}");
+                if let Some(report) = weather.as_mut() {
+                    report.stale = true;
+                }
+                None
+            }
+        }
+    };
+    broadcast_weather(app_state);
+
+    // overrides are never stored, the history only holds what the station saw
+    if let Some(observation) = observation {
+        if let Err(error) = observation
+            .create(&app_state.pool, &app_state.config.weather_station_id)
+            .await
+        {
+            error!("Failed to store weather observation: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use model::enums::WeatherCondition;
+    use model::ws_msg::WSMsg;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    async fn serve_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let check_query = |query: &HashMap<String, String>| {
+            query.get("Authorization").map(String::as_str) == Some("test-key")
+                && query.get("StationId").map(String::as_str) == Some("C0C63")
+        };
+        let app = Router::new()
+            .route(
+                "/O-A0001-001",
+                get(
+                    move |Query(query): Query<HashMap<String, String>>| async move {
+                        if !check_query(&query) {
+                            return Err(StatusCode::UNAUTHORIZED);
+                        }
+                        Ok(include_str!("../model/fixtures/weather/O-A0001-001.json"))
+                    },
+                ),
+            )
+            .route(
+                "/O-A0002-001",
+                get(|| async { include_str!("../model/fixtures/weather/O-A0002-001.json") }),
+            )
+            .route(
+                "/page/C0C63.html",
+                get(|| async { include_str!("../model/fixtures/weather/C0C63_sunny.html") }),
+            );
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base_url
+    }
+
+    fn get_providers(base_url: &str, api_key: &str) -> Vec<Box<dyn WeatherProvider>> {
+        vec![
+            Box::new(CwaApiProvider::new(base_url, api_key, "C0C63")),
+            Box::new(CwaPageProvider::new(
+                &format!("{base_url}/page/{{station}}.html"),
+                "C0C63",
+            )),
+        ]
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_page() {
+        let base_url = serve_stub().await;
+
+        let observation = fetch_observation(&get_providers(&base_url, "test-key"))
+            .await
+            .unwrap();
+        assert_eq!(observation.description.as_deref(), Some("多雲"));
+        assert_eq!(observation.condition, WeatherCondition::Rainy);
+
+        // the API rejects the key, the page still works
+        let observation = fetch_observation(&get_providers(&base_url, "wrong-key"))
+            .await
+            .unwrap();
+        assert_eq!(observation.description.as_deref(), Some("晴"));
+
+        assert!(fetch_observation(&[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rebroadcast_when_the_override_expires() {
+        let app_state = AppState::for_tests();
+        let observation = WeatherObservation::new(Utc::now(), None, None, None, None, None);
+        *app_state.weather.lock().unwrap() = Some(WeatherReport::new(observation));
+        let (mut rx, _, _) = app_state.ws_sender.subscribe(None);
+
+        let until = Utc::now() + Duration::from_millis(200);
+        set_override(
+            &app_state,
+            Some(WeatherOverride {
+                condition: None,
+                wind_level: None,
+                rain_level: None,
+                until,
+            }),
+        );
+
+        let overridden = |msg: WSMsg| match msg.data {
+            ServerMessage::Weather(report) => report.overridden,
+            data => panic!("unexpected message {data:?}"),
+        };
+        assert!(overridden(rx.recv().await.unwrap()));
+        let expired = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(Utc::now() >= until);
+        assert!(!overridden(expired));
+        assert_eq!(get_active_override(&app_state), None);
     }
 }
@@ -2,6 +2,17 @@ use std::env;
 
 pub fn get_env(key: &str) -> String {
     env::var(key).unwrap_or_else(|e| {
-        dotenvy::var(key).expect(format!("Error while finding env var `{key}`: {:?}", e).as_str())
+        dotenvy::var(key).unwrap_or_else(|_| panic!("Error while finding env var `{key}`: {e:?}"))
     })
 }
+
+pub fn get_env_opt(key: &str) -> Option<String> {
+    env::var(key)
+        .ok()
+        .or_else(|| dotenvy::var(key).ok())
+        .filter(|value| !value.is_empty())
+}
+
+pub fn get_env_or(key: &str, default: &str) -> String {
+    get_env_opt(key).unwrap_or_else(|| default.to_string())
+}
@@ -1,5 +1,8 @@
 use bytes::Bytes;
-use image::{GenericImageView, ImageBuffer, ImageFormat, Rgb};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{GenericImageView, ImageBuffer, ImageFormat, ImageResult, Rgb};
+use std::collections::HashMap;
 
 const BG_COLOR: Rgb<u8> = Rgb([172, 138, 106]);
 // "#AC8A6A"
@@ -31,3 +34,96 @@ pub fn generate_texture(id: &str, bytes: &Bytes, path: &str) {
     bg.save_with_format(format!("{path}/texture/{id}.jpg"), ImageFormat::Jpeg)
         .expect("Failed to save texture image");
 }
+
+/// Downscale an image so its longest edge is at most `max_edge` and re-encode it as a JPEG
+/// with the given quality, ready to be sent to the vision model.
+pub fn compress_for_vision(bytes: &[u8], max_edge: u32, quality: u8) -> ImageResult<Vec<u8>> {
+    let mut img = image::load_from_memory(bytes)?;
+
+    let (w, h) = img.dimensions();
+    if w.max(h) > max_edge {
+        img = img.resize(max_edge, max_edge, FilterType::Triangle);
+    }
+
+    let mut buf = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut buf, quality);
+    img.to_rgb8().write_with_encoder(encoder)?;
+
+    Ok(buf)
+}
+
+/// Find the most common colour of a texture, ignoring the background it was painted on.
+pub fn dominant_color(bytes: &[u8]) -> Option<[u8; 3]> {
+    let img = image::load_from_memory(bytes)
+        .ok()?
+        .thumbnail(64, 64)
+        .to_rgb8();
+
+    // bucket pixels into 4 levels per channel, summing the real colours for averaging later
+    let mut buckets: HashMap<(u8, u8, u8), (u32, [u32; 3])> = HashMap::new();
+    for pixel in img.pixels() {
+        let distance: i32 = (0..3)
+            .map(|i| (pixel[i] as i32 - BG_COLOR[i] as i32).abs())
+            .sum();
+        if distance < 40 {
+            continue;
+        }
+
+        let bucket = buckets
+            .entry((pixel[0] >> 6, pixel[1] >> 6, pixel[2] >> 6))
+            .or_insert((0, [0; 3]));
+        bucket.0 += 1;
+        for i in 0..3 {
+            bucket.1[i] += pixel[i] as u32;
+        }
+    }
+
+    let (count, sum) = buckets.into_values().max_by_key(|(count, _)| *count)?;
+    Some(sum.map(|channel| (channel / count) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dominant_color_ignores_background() {
+        let mut img = ImageBuffer::from_pixel(64, 64, BG_COLOR);
+        for x in 0..20 {
+            for y in 0..20 {
+                img.put_pixel(x, y, Rgb([20, 40, 200]));
+            }
+        }
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        assert_eq!(dominant_color(&png), Some([20, 40, 200]));
+    }
+
+    #[test]
+    fn compress_for_vision_keeps_aspect_ratio() {
+        let img = ImageBuffer::from_pixel(1200, 600, BG_COLOR);
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let jpeg = compress_for_vision(&png, 512, 80).unwrap();
+        let result = image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg).unwrap();
+
+        assert_eq!(result.dimensions(), (512, 256));
+    }
+
+    #[test]
+    fn compress_for_vision_does_not_upscale() {
+        let img = ImageBuffer::from_pixel(100, 100, BG_COLOR);
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let jpeg = compress_for_vision(&png, 512, 80).unwrap();
+        let result = image::load_from_memory(&jpeg).unwrap();
+
+        assert_eq!(result.dimensions(), (100, 100));
+    }
+}
@@ -1,8 +1,7 @@
 // See https://kit.svelte.dev/docs/types#app
 // for information about these interfaces
 
-import type { ParseEnum } from '@2enter/web-kit/types';
-import type { CargoType } from '@/types/model';
+import type { WSMsg } from '@/types/model';
 
 declare global {
 	namespace App {
@@ -13,28 +12,8 @@ declare global {
 		// interface Platform {}
 	}
 	type PageNum = 0 | 1 | 2 | 3 | 4 | 5 | 6;
-	type WSData = {
-		message?: string;
-		data?:
-			| {
-					type: 'cargo';
-					cargo_type: ParseEnum<CargoType>;
-					id: string;
-					directory: string;
-			  }
-			| {
-					type: 'launch';
-					cargo_amount: number;
-			  }
-			| {
-					type: 'weather';
-					raining: boolean;
-			  }
-			| {
-					type: 'population';
-					amount: number;
-			  };
-	};
+	/** A server `WSMsg`, the envelope may also carry a plain text `message` */
+	type WSData = Partial<WSMsg> & { message?: string };
 }
 
 export {};
@@ -1,4 +1,13 @@
-import type { Cargo, CargoRequest } from '@/types/model';
+import type {
+	Cargo,
+	CargoRequest,
+	News,
+	NewsKeyword,
+	NewsRank,
+	NewsSearchResult,
+	WeatherHourly,
+	WeatherReport
+} from '@/types/model';
 
 import axios from 'axios';
 import { Api } from '@2enter/web-kit/runtime';
@@ -6,14 +15,40 @@ import { objToFD } from '@2enter/web-kit/calc';
 
 const api = new Api(axios.create());
 
-async function getNews() {
-	return api.fetch<string[]>({ url: '/api/news' });
+async function getNews(rank?: NewsRank) {
+	return api.fetch<News[]>({ url: '/api/news/items', params: { rank } });
+}
+
+async function getNewsTitles(rank?: NewsRank) {
+	return api.fetch<string[]>({ url: '/api/news', params: { rank } });
+}
+
+async function getNewsKeywords() {
+	return api.fetch<NewsKeyword[]>({ url: '/api/news/keywords' });
+}
+
+async function searchNews(params: {
+	q?: string;
+	from?: string;
+	to?: string;
+	page?: number;
+	pageSize?: number;
+}) {
+	return api.fetch<NewsSearchResult>({ url: '/api/news/search', params });
 }
 
 async function getSysTemp() {
 	return api.fetch<number>({ url: '/api/sys-temp' });
 }
 
+async function getWeather() {
+	return api.fetch<WeatherReport>({ url: '/api/weather' });
+}
+
+async function getWeatherHistory(params: { from?: string; to?: string }) {
+	return api.fetch<WeatherHourly[]>({ url: '/api/weather/history', params });
+}
+
 async function postCargo(input: CargoRequest) {
 	const fd = objToFD({ ...input });
 	return api.fetch<Cargo>({ url: '/api/cargo', method: 'post', data: fd });
@@ -31,4 +66,16 @@ async function getCargoById(id: string) {
 	return api.fetch<Cargo>({ url: `/api/cargo/${id}` });
 }
 
-export { getCargoes, getNews, getSysTemp, getTodayCargoes, postCargo, getCargoById };
+export {
+	getCargoes,
+	getNews,
+	getNewsKeywords,
+	getNewsTitles,
+	getSysTemp,
+	getTodayCargoes,
+	getWeather,
+	getWeatherHistory,
+	postCargo,
+	getCargoById,
+	searchNews
+};
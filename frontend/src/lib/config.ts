@@ -1,3 +1,6 @@
+import { dev } from '$app/environment';
+import type { WSTopic } from '@/types/model';
+
 const RESULT_TESTING = false;
 
 const COLORS = [
@@ -23,6 +26,13 @@ const DEFAULT_CRON_CONFIG = {
 	runOnInit: true
 } as const;
 
-export { COLORS, DEFAULT_CRON_CONFIG, RESULT_TESTING };
+/** `topics` limits what the server sends, every topic when omitted */
+function getWSUrl(hostname: string, topics?: `${WSTopic}`[]) {
+	const query = topics ? `?topics=${topics.join(',')}` : '';
+	if (!dev && hostname.includes('2enter')) return `wss://${hostname}/ws${query}`;
+	return `ws://${hostname}:3000/ws${query}`;
+}
+
+export { COLORS, DEFAULT_CRON_CONFIG, RESULT_TESTING, getWSUrl };
 
 export type { ColorName };
@@ -17,6 +17,12 @@ export enum CargoStatus {
 	Launched = "launched",
 }
 
+export enum CargoTextSource {
+	Ai = "ai",
+	Procedural = "procedural",
+	Manual = "manual",
+}
+
 export interface Cargo {
 	id: string;
 	createdAt: Date;
@@ -25,6 +31,11 @@ export interface Cargo {
 	status: CargoStatus;
 	name: string | null;
 	description: string | null;
+	textSource: CargoTextSource | null;
+	suggestedType: CargoType | null;
+	typeConfidence: number | null;
+	flagReason: string | null;
+	filterLog: string | null;
 }
 
 export interface CargoInput {
@@ -37,3 +48,298 @@ export interface CargoRequest {
 	paintTime: number;
 	file: Blob;
 }
+
+/** Which cargoes a batch text generation should cover */
+export enum BackfillScope {
+	/** cargoes without any name or description */
+	Undocumented = "undocumented",
+	/** cargoes described by the offline generator */
+	Procedural = "procedural",
+	/** every cargo except manually written ones, e.g. after a prompt change */
+	All = "all",
+}
+
+export interface TextBatchRequest {
+	scope: BackfillScope;
+}
+
+/** A message batch submitted to the AI provider, tracked until its results are applied */
+export interface TextBatch {
+	id: string;
+	createdAt: Date;
+	endedAt: Date | null;
+	cargoIds: string[];
+}
+
+/** How news are ordered, `decay` weighs hype against age */
+export enum NewsRank {
+	Decay = "decay",
+	Hype = "hype",
+	Recent = "recent",
+}
+
+export interface News {
+	id: string;
+	createdAt: Date;
+	updatedAt: Date;
+	hype: number;
+	title: string;
+	url: string | null;
+	publisher: string | null;
+	publishedAt: Date | null;
+	language: string | null;
+	topic: string | null;
+	/** the story this is a rewrite of, `None` for canonical stories */
+	canonicalId: string | null;
+	/** hidden by the staff, never shown in the ticker */
+	hidden: boolean;
+	/** shown before the ranked news until then */
+	pinnedUntil: Date | null;
+	/** written by the staff instead of fetched, ranked right after the pinned news */
+	manual: boolean;
+}
+
+/** A headline written by the staff */
+export interface ManualNewsRequest {
+	title: string;
+	url: string | null;
+	publisher: string | null;
+	pinnedUntil: Date | null;
+}
+
+export interface NewsSearchResult {
+	items: News[];
+	total: number;
+	page: number;
+	pageSize: number;
+}
+
+/** A word of the trending news and its hype-weighted frequency */
+export interface NewsKeyword {
+	word: string;
+	weight: number;
+}
+
+export interface NewsPinRequest {
+	until: Date;
+}
+
+/** Statistics of one news fetch */
+export interface NewsFetchRun {
+	id: string;
+	createdAt: Date;
+	/** distinct titles returned by the sources */
+	found: number;
+	/** titles seen for the first time */
+	inserted: number;
+	/** titles already stored, their hype was bumped */
+	bumped: number;
+	outcome: NewsFetchOutcome;
+	/** one line per failed source */
+	errors: string | null;
+}
+
+export enum NewsFetchOutcome {
+	Ok = "ok",
+	/** no source returned any news, usually the markup changed */
+	Empty = "empty",
+	/** some sources failed, the news of the others were stored */
+	Partial = "partial",
+	/** every source returned nothing or failed, or storing the news failed */
+	Failed = "failed",
+}
+
+/** What one source returned during a fetch */
+export interface NewsSourceRun {
+	runId: string;
+	source: string;
+	/** titles returned, before they are merged with the other sources */
+	found: number;
+	error: string | null;
+}
+
+/** Health of one news source, unhealthy after too many consecutive empty or failed fetches */
+export interface NewsSourceHealth {
+	source: string;
+	healthy: boolean;
+	consecutiveFailures: number;
+	warning: string | null;
+}
+
+/** Health of the news fetching, unhealthy once any source of the last run is */
+export interface NewsHealth {
+	healthy: boolean;
+	/** one line per unhealthy source */
+	warning: string | null;
+	lastRun: NewsFetchRun | null;
+	sources: NewsSourceHealth[];
+}
+
+/** Overview for the staff, whatever needs attention shows up here */
+export interface AdminStatus {
+	news: NewsHealth;
+	/** `None` while the live weather is shown */
+	weatherOverride: WeatherOverride | null;
+}
+
+/** What the CCTV screen shows, cloudy shares the sunny background */
+export enum WeatherCondition {
+	Sunny = "sunny",
+	Cloudy = "cloudy",
+	Rainy = "rainy",
+}
+
+/** Wind speed bucket, matches the `wind_{slow,medium,fast}` CCTV assets */
+export enum WindLevel {
+	/** up to a gentle breeze, below 3.4 m/s */
+	Slow = "slow",
+	/** below 8 m/s */
+	Medium = "medium",
+	Fast = "fast",
+}
+
+export enum RainLevel {
+	Dry = "dry",
+	Light = "light",
+	/** heavy rain or thunderstorms, at least 7.6 mm per hour */
+	Heavy = "heavy",
+}
+
+/** One hourly observation of a weather station */
+export interface WeatherObservation {
+	observedAt: Date;
+	condition: WeatherCondition;
+	/** as the station reports it, e.g. 陰有雨 */
+	description: string | null;
+	/** °C */
+	temperature: number | null;
+	/** m/s */
+	windSpeed: number | null;
+	windLevel: WindLevel;
+	/** mm during the last hour */
+	rainfall: number | null;
+	rainLevel: RainLevel;
+	/** relative humidity in percent */
+	humidity: number | null;
+}
+
+/** The last successful observation, kept while the providers are failing */
+export interface WeatherReport {
+	observation: WeatherObservation;
+	fetchedAt: Date;
+	/** the latest fetch failed, the observation may be outdated */
+	stale: boolean;
+	/** the observation was forced by the staff */
+	overridden: boolean;
+}
+
+/** Weather forced by the staff during demos and rehearsals, unset fields keep the live values */
+export interface WeatherOverride {
+	condition: WeatherCondition | null;
+	windLevel: WindLevel | null;
+	rainLevel: RainLevel | null;
+	until: Date;
+}
+
+/** The observations of one hour, the most frequent condition and the strongest wind and rain */
+export interface WeatherHourly {
+	hour: Date;
+	observations: number;
+	condition: WeatherCondition;
+	/** average °C */
+	temperature: number | null;
+	temperatureMin: number | null;
+	temperatureMax: number | null;
+	/** average m/s */
+	windSpeed: number | null;
+	windLevel: WindLevel;
+	/** the largest hourly rainfall reported, in mm */
+	rainfall: number | null;
+	rainLevel: RainLevel;
+	/** average relative humidity in percent */
+	humidity: number | null;
+}
+
+/** A cargo was sent, its texture is ready at `directory` */
+export interface CargoEvent {
+	cargoType: CargoType;
+	id: string;
+	directory: string;
+}
+
+export interface LaunchEvent {
+	cargoAmount: number;
+}
+
+/** Visitors counted by the camera at the entrance */
+export interface PopulationEvent {
+	amount: number;
+}
+
+/**
+ * Everything a display shows, sent first when a socket connects.
+ * The fields of topics the client did not subscribe to are left empty
+ */
+export interface SnapshotEvent {
+	/** `None` until the first observation, unless overridden */
+	weather: WeatherReport | null;
+	/** `None` until the visitor counter reports */
+	population: PopulationEvent | null;
+	secondsToLaunch: number | null;
+	/** delivered cargoes waiting for the next launch, oldest first */
+	cargoes: CargoEvent[];
+	news: News[];
+}
+
+/** What a client can subscribe to, one per `ServerMessage` type */
+export enum WSTopic {
+	Cargo = "cargo",
+	Launch = "launch",
+	Weather = "weather",
+	Population = "population",
+	News = "news",
+}
+
+/** Broadcast from the server to every display */
+export type ServerMessage = 
+	| { type: "cargo", content: CargoEvent }
+	| { type: "launch", content: LaunchEvent }
+	| { type: "weather", content: WeatherReport }
+	| { type: "population", content: PopulationEvent }
+	/** the current top news of the ticker */
+	| { type: "news", content: News[] }
+	/** only sent to a client that just connected */
+	| { type: "snapshot", content: SnapshotEvent }
+	/**
+	 * sent instead of the events a client missed when they are no longer kept,
+	 * replaces everything the client shows
+	 */
+	| { type: "resync", content: SnapshotEvent };
+
+/** Sent by clients, e.g. the visitor counter */
+export type ClientMessage = 
+	/** rebroadcast to every display */
+	| { type: "population", content: PopulationEvent }
+	/**
+	 * receive these topics as well, a client still receiving every topic
+	 * switches to only these
+	 */
+	| { type: "subscribe", content: WSTopic[] }
+	/** stop receiving these topics */
+	| { type: "unsubscribe", content: WSTopic[] };
+
+/** What goes over the socket from the server */
+export interface WSMsg {
+	version: number;
+	/** picked at random when the server starts, events are numbered anew after a restart */
+	boot: number;
+	/** the number of the event, a snapshot has the number of the last event it includes */
+	seq: number;
+	data: ServerMessage;
+}
+
+/** What goes over the socket from a client */
+export interface WSClientMsg {
+	version: number;
+	data: ClientMessage;
+}